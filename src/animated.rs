@@ -0,0 +1,175 @@
+//! Keyframed parameters
+//!
+//! An [`Animated`] value is either constant or interpolated between keyframes
+//! placed on the animation level, and is evaluated by `draw` for every frame.
+
+use crate::{Easing, ElectronBeamError, Result};
+
+/// A value that can be interpolated between keyframes
+pub trait Lerp: Copy {
+    /// Interpolate from `self` to `other`, `t` going from 0.0 to 1.0
+    fn lerp(self, other: Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for [u8; 3] {
+    fn lerp(self, other: Self, t: f32) -> Self {
+        std::array::from_fn(|i| (self[i] as f32).lerp(other[i] as f32, t).round() as u8)
+    }
+}
+
+/// A parameter value at one animation level
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keyframe<T> {
+    /// Animation level of the keyframe, from 0.0 to 1.0
+    pub level: f32,
+    pub value: T,
+    /// Easing of the interpolation towards the next keyframe
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub easing: Easing,
+}
+
+impl<T> Keyframe<T> {
+    /// A keyframe interpolated linearly towards the next one
+    pub fn new(level: f32, value: T) -> Self {
+        Self {
+            level,
+            value,
+            easing: Easing::Linear,
+        }
+    }
+
+    /// Set the easing towards the next keyframe
+    pub fn eased(self, easing: Easing) -> Self {
+        Self { easing, ..self }
+    }
+}
+
+/// A parameter that is either constant or keyframed over the animation level
+///
+/// Before the first keyframe the value is held at the first one, and after
+/// the last at the last one. Plain values convert with `into()`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum Animated<T> {
+    Constant(T),
+    Keyframes(Vec<Keyframe<T>>),
+}
+
+impl<T: Lerp> Animated<T> {
+    /// Keyframes at `(level, value)` pairs, interpolated linearly
+    pub fn keyframes(keyframes: impl IntoIterator<Item = (f32, T)>) -> Self {
+        Animated::Keyframes(
+            keyframes
+                .into_iter()
+                .map(|(level, value)| Keyframe::new(level, value))
+                .collect(),
+        )
+    }
+
+    /// The value at an animation level
+    ///
+    /// Panics on an empty keyframe list, which validation rejects.
+    pub fn at(&self, level: f32) -> T {
+        let keyframes = match self {
+            Animated::Constant(value) => return *value,
+            Animated::Keyframes(keyframes) => keyframes,
+        };
+
+        let next = keyframes.partition_point(|keyframe| keyframe.level <= level);
+        match (
+            next.checked_sub(1).map(|i| &keyframes[i]),
+            keyframes.get(next),
+        ) {
+            (Some(from), Some(to)) => {
+                let t = (level - from.level) / (to.level - from.level);
+                from.value.lerp(to.value, from.easing.apply(t))
+            }
+            (Some(last), None) => last.value,
+            (None, Some(first)) => first.value,
+            (None, None) => unreachable!("validated keyframes are not empty"),
+        }
+    }
+
+    /// Every value the parameter takes at a keyframe
+    pub fn values(&self) -> impl Iterator<Item = T> + '_ {
+        let (constant, keyframes) = match self {
+            Animated::Constant(value) => (Some(*value), &[][..]),
+            Animated::Keyframes(keyframes) => (None, &keyframes[..]),
+        };
+        constant
+            .into_iter()
+            .chain(keyframes.iter().map(|keyframe| keyframe.value))
+    }
+
+    /// Check that keyframes exist and are in increasing level order within 0..1
+    pub(crate) fn validate(&self, field: &'static str) -> Result<()> {
+        if let Animated::Keyframes(keyframes) = self {
+            let in_range = keyframes
+                .iter()
+                .all(|keyframe| (0.0..=1.0).contains(&keyframe.level));
+            let increasing = keyframes.windows(2).all(|w| w[0].level < w[1].level);
+            if keyframes.is_empty() || !in_range || !increasing {
+                return Err(ElectronBeamError::InvalidKeyframes(field));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> From<T> for Animated<T> {
+    fn from(value: T) -> Self {
+        Animated::Constant(value)
+    }
+}
+
+impl<T: Default> Default for Animated<T> {
+    fn default() -> Self {
+        Animated::Constant(T::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpolation() {
+        let split = Animated::keyframes([(0.8, 0.0), (1.0, 2.0)]);
+        assert_eq!(split.at(0.0), 0.0);
+        assert_eq!(split.at(0.8), 0.0);
+        assert!((split.at(0.9) - 1.0).abs() < 1e-6);
+        assert_eq!(split.at(1.0), 2.0);
+
+        let tint = Animated::Keyframes(vec![
+            Keyframe::new(0.0, [0, 0, 0]).eased(Easing::EaseIn),
+            Keyframe::new(1.0, [200, 100, 255]),
+        ]);
+        assert_eq!(tint.at(0.5), [50, 25, 64]);
+        assert_eq!(Animated::from(3.0).at(0.4), 3.0);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Animated::keyframes([(0.0, 1.0), (0.5, 2.0)])
+            .validate("x")
+            .is_ok());
+        for invalid in [
+            Animated::Keyframes(Vec::new()),
+            Animated::keyframes([(0.5, 1.0), (0.5, 2.0)]),
+            Animated::keyframes([(0.0, 1.0), (1.5, 2.0)]),
+        ] {
+            assert!(matches!(
+                invalid.validate("x"),
+                Err(ElectronBeamError::InvalidKeyframes("x"))
+            ));
+        }
+    }
+}
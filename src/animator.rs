@@ -0,0 +1,298 @@
+//! Real-time playback
+//!
+//! The renderer only understands a normalised level. [`Animator`] maps wall
+//! clock time onto that level over a fixed duration, the way the display
+//! power controller drives the Java `ScreenStateAnimator`, and can run the
+//! effect live at a steady frame rate.
+
+use std::cell::Cell;
+use std::error::Error;
+use std::time::{Duration, Instant};
+
+use image::RgbaImage;
+
+use crate::{ElectronBeamError, PreparedBeam, Result};
+
+/// Source of the current time for an [`Animator`]
+pub trait Clock {
+    /// The current instant
+    fn now(&self) -> Instant;
+
+    /// Block until `deadline`, returning immediately if it has passed
+    fn sleep_until(&self, deadline: Instant) {
+        let now = self.now();
+        if deadline > now {
+            std::thread::sleep(deadline - now);
+        }
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// The monotonic system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock that only moves when told to, for tests and offline rendering
+///
+/// Sleeping advances the clock to the deadline instead of blocking. Pass it
+/// by reference to keep control of it while an animator is running.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    now: Cell<Instant>,
+}
+
+impl ManualClock {
+    /// Create a clock stopped at the current instant
+    pub fn new() -> Self {
+        Self {
+            now: Cell::new(Instant::now()),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        if deadline > self.now.get() {
+            self.now.set(deadline);
+        }
+    }
+}
+
+/// Drives the effect level from elapsed time
+///
+/// The level rises linearly from 0.0 when the animator is started to 1.0
+/// once `duration` has elapsed, then stays there.
+#[derive(Debug, Clone)]
+pub struct Animator<C: Clock = SystemClock> {
+    duration: Duration,
+    clock: C,
+    start: Option<Instant>,
+}
+
+impl Animator {
+    /// Create an animator running on the system clock
+    pub fn new(duration: Duration) -> Self {
+        Self::with_clock(duration, SystemClock)
+    }
+}
+
+impl<C: Clock> Animator<C> {
+    /// Create an animator running on a custom clock
+    pub fn with_clock(duration: Duration, clock: C) -> Self {
+        Self {
+            duration,
+            clock,
+            start: None,
+        }
+    }
+
+    /// Total duration of the effect
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Start, or restart, the effect now
+    pub fn start(&mut self) {
+        self.start = Some(self.clock.now());
+    }
+
+    /// Instant the effect was started at, if it has been
+    pub fn started_at(&self) -> Option<Instant> {
+        self.start
+    }
+
+    /// Time since the effect was started, zero if it has not been
+    pub fn elapsed(&self) -> Duration {
+        self.start.map_or(Duration::ZERO, |start| {
+            self.clock.now().saturating_duration_since(start)
+        })
+    }
+
+    /// Level after `elapsed` time into the effect, clamped to 0.0..=1.0
+    pub fn level_at(&self, elapsed: Duration) -> f32 {
+        if self.duration.is_zero() {
+            return 1.0;
+        }
+        (elapsed.as_secs_f64() / self.duration.as_secs_f64()).min(1.0) as f32
+    }
+
+    /// Level at `instant`, 0.0 if the effect has not been started
+    pub fn level_at_instant(&self, instant: Instant) -> f32 {
+        match self.start {
+            Some(start) => self.level_at(instant.saturating_duration_since(start)),
+            None => 0.0,
+        }
+    }
+
+    /// Level right now according to the clock
+    pub fn level(&self) -> f32 {
+        self.level_at_instant(self.clock.now())
+    }
+
+    /// Whether the effect has been started and has run its full duration
+    pub fn is_finished(&self) -> bool {
+        self.start.is_some() && self.elapsed() >= self.duration
+    }
+
+    /// Play the effect in real time, presenting a frame every `frame_interval`
+    ///
+    /// Starts the animator and renders the current level into a reused
+    /// buffer on every tick, passing it to `present`. Ticks that were missed
+    /// because a frame took too long are skipped rather than queued. The last
+    /// frame presented is always the final level of 1.0. Returns the number
+    /// of frames presented, or the first error; errors from `present` are
+    /// reported as [`ElectronBeamError::Callback`].
+    pub fn run<F>(
+        &mut self,
+        beam: &PreparedBeam,
+        frame_interval: Duration,
+        mut present: F,
+    ) -> Result<u32>
+    where
+        F: FnMut(&RgbaImage) -> Result<(), Box<dyn Error + Send + Sync>>,
+    {
+        let config = beam.config();
+        let mut frame = RgbaImage::new(config.width, config.height);
+
+        let start = self.clock.now();
+        self.start = Some(start);
+        let mut presented = 0;
+        let mut tick = 0u32;
+
+        loop {
+            let level = self.level();
+            beam.draw_into(level, &mut frame)?;
+            present(&frame).map_err(|err| ElectronBeamError::Callback {
+                frame: presented,
+                source: err,
+            })?;
+            presented += 1;
+
+            if level >= 1.0 {
+                return Ok(presented);
+            }
+
+            // Next tick after now, skipping any that were missed
+            let elapsed = self.clock.now().saturating_duration_since(start);
+            let interval = frame_interval.max(Duration::from_micros(1));
+            tick = tick.max((elapsed.as_nanos() / interval.as_nanos()) as u32) + 1;
+            let deadline = (start + interval * tick).min(start + self.duration);
+            self.clock.sleep_until(deadline);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimationMode, ElectronBeamBuilder};
+    use image::ImageBuffer;
+
+    #[test]
+    fn test_level_follows_clock() {
+        let clock = ManualClock::new();
+        let mut animator = Animator::with_clock(Duration::from_millis(400), &clock);
+
+        assert_eq!(animator.level(), 0.0);
+        assert!(!animator.is_finished());
+
+        animator.start();
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(animator.level(), 0.25);
+        assert_eq!(animator.elapsed(), Duration::from_millis(100));
+
+        let start = animator.started_at().unwrap();
+        assert_eq!(
+            animator.level_at_instant(start + Duration::from_millis(300)),
+            0.75
+        );
+        assert_eq!(animator.level_at(Duration::from_secs(9)), 1.0);
+
+        clock.advance(Duration::from_millis(300));
+        assert!(animator.is_finished());
+        assert_eq!(animator.level(), 1.0);
+    }
+
+    #[test]
+    fn test_run_presents_every_tick() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(8, 8)
+            .mode(AnimationMode::Fade)
+            .prepare(ImageBuffer::from_pixel(
+                8,
+                8,
+                image::Rgba([255, 255, 255, 255]),
+            ))
+            .unwrap();
+
+        let clock = ManualClock::new();
+        let mut animator = Animator::with_clock(Duration::from_millis(100), &clock);
+        let mut alphas = Vec::new();
+        let presented = animator
+            .run(&beam, Duration::from_millis(25), |frame| {
+                alphas.push(frame.get_pixel(0, 0)[3]);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(presented, 5);
+        assert_eq!(alphas.first(), Some(&255));
+        assert_eq!(alphas.last(), Some(&0));
+        assert!(animator.is_finished());
+    }
+
+    #[test]
+    fn test_run_reports_present_errors() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(8, 8)
+            .prepare(ImageBuffer::new(8, 8))
+            .unwrap();
+
+        let clock = ManualClock::new();
+        let mut animator = Animator::with_clock(Duration::from_millis(100), &clock);
+        let error = animator
+            .run(&beam, Duration::from_millis(25), |_| {
+                Err("display lost".into())
+            })
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Frame callback failed on frame 1");
+        match error {
+            ElectronBeamError::Callback { frame, source } => {
+                assert_eq!(frame, 0);
+                assert_eq!(source.to_string(), "display lost");
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+}
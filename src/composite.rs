@@ -0,0 +1,211 @@
+//! Composite (NTSC) video signal artifacts
+//!
+//! Simulates a picture that went through an RF or composite cable before
+//! reaching the tube: the image is split into YIQ, the chroma is band-limited
+//! and smeared to the right, and the luma picks up dot crawl from the colour
+//! subcarrier plus ringing around sharp edges.
+
+use std::f32::consts::PI;
+
+use image::RgbaImage;
+use palette::Srgb;
+
+/// Number of subcarrier cycles the dot crawl pattern moves over a full animation
+const CRAWL_CYCLES: f32 = 15.0;
+
+/// Largest chroma blur radius in pixels; the Q signal is blurred twice as wide
+pub(crate) const MAX_CHROMA_BLUR: u32 = 256;
+
+/// Settings for the composite video pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct CompositeConfig {
+    /// Horizontal blur radius of the chroma signal in pixels (bandwidth limiting, at most 256)
+    pub chroma_blur: u32,
+    /// Horizontal delay of the chroma signal in pixels (colour bleed)
+    pub chroma_bleed: f32,
+    /// Amplitude of the dot crawl pattern the chroma leaves on the luma (0.0 = off)
+    pub dot_crawl: f32,
+    /// Strength of the overshoot around sharp luma edges (0.0 = off)
+    pub ringing: f32,
+}
+
+impl Default for CompositeConfig {
+    fn default() -> Self {
+        Self {
+            chroma_blur: 2,
+            chroma_bleed: 1.0,
+            dot_crawl: 0.1,
+            ringing: 0.3,
+        }
+    }
+}
+
+/// Apply the composite pass to row `y` of an image, writing into `output`
+///
+/// `output` holds one row of `source` in RGBA. Rows are independent, so the
+/// pass can run on many rows at once. `level` is the animation level of the
+/// frame and drives the dot crawl phase.
+pub(crate) fn apply_row(
+    source: &RgbaImage,
+    config: &CompositeConfig,
+    level: f32,
+    y: u32,
+    output: &mut [u8],
+) {
+    let width = source.width() as usize;
+    debug_assert_eq!(output.len(), width * 4);
+    let crawl_phase = level * CRAWL_CYCLES * 2.0 * PI;
+    let input = &source.as_raw()[y as usize * width * 4..][..width * 4];
+
+    let mut rows = vec![0.0; width * 7];
+    let (y_row, rest) = rows.split_at_mut(width);
+    let (i_signal, rest) = rest.split_at_mut(width);
+    let (q_signal, rest) = rest.split_at_mut(width);
+    let (blurred, rest) = rest.split_at_mut(width);
+    let (i_row, rest) = rest.split_at_mut(width);
+    let (q_row, smooth) = rest.split_at_mut(width);
+
+    for (x, pixel) in input.chunks_exact(4).enumerate() {
+        let rgb: Srgb<f32> = Srgb::new(pixel[0], pixel[1], pixel[2]).into_format();
+        [y_row[x], i_signal[x], q_signal[x]] = rgb_to_yiq(rgb);
+    }
+
+    box_blur(i_signal, config.chroma_blur, blurred);
+    delay(blurred, config.chroma_bleed, i_row);
+    box_blur(q_signal, config.chroma_blur * 2, blurred);
+    delay(blurred, config.chroma_bleed, q_row);
+    box_blur(y_row, 1, smooth);
+
+    for (x, (out, pixel)) in output
+        .chunks_exact_mut(4)
+        .zip(input.chunks_exact(4))
+        .enumerate()
+    {
+        // Adjacent lines are half a subcarrier cycle apart, which gives the
+        // familiar checkerboard that crawls from frame to frame
+        let phase = (x as f32 * 0.5 + y as f32) * PI + crawl_phase;
+        let crawl = config.dot_crawl * (i_row[x] * phase.cos() + q_row[x] * phase.sin());
+        let ring = config.ringing * (y_row[x] - smooth[x]);
+        let luma = y_row[x] + crawl + ring;
+
+        let rgb: Srgb<u8> = yiq_to_rgb([luma, i_row[x], q_row[x]]).into_format();
+        out.copy_from_slice(&[rgb.red, rgb.green, rgb.blue, pixel[3]]);
+    }
+}
+
+/// Convert gamma-encoded RGB to the NTSC YIQ colour space
+fn rgb_to_yiq(rgb: Srgb<f32>) -> [f32; 3] {
+    let (r, g, b) = rgb.into_components();
+    [
+        0.299 * r + 0.587 * g + 0.114 * b,
+        0.5959 * r - 0.2746 * g - 0.3213 * b,
+        0.2115 * r - 0.5227 * g + 0.3112 * b,
+    ]
+}
+
+/// Convert NTSC YIQ back to gamma-encoded RGB, clamped to the displayable range
+fn yiq_to_rgb([y, i, q]: [f32; 3]) -> Srgb<f32> {
+    Srgb::new(
+        (y + 0.956 * i + 0.619 * q).clamp(0.0, 1.0),
+        (y - 0.272 * i - 0.647 * q).clamp(0.0, 1.0),
+        (y - 1.106 * i + 1.703 * q).clamp(0.0, 1.0),
+    )
+}
+
+/// Horizontal box blur with clamped edges
+fn box_blur(row: &[f32], radius: u32, output: &mut [f32]) {
+    if radius == 0 {
+        output.copy_from_slice(row);
+        return;
+    }
+
+    let radius = radius as isize;
+    let last = row.len() as isize - 1;
+    for (x, out) in output.iter_mut().enumerate() {
+        let x = x as isize;
+        let sum: f32 = (x - radius..=x + radius)
+            .map(|sx| row[sx.clamp(0, last) as usize])
+            .sum();
+        *out = sum / (2 * radius + 1) as f32;
+    }
+}
+
+/// Shift a row to the right by a fractional number of pixels
+fn delay(row: &[f32], pixels: f32, output: &mut [f32]) {
+    let last = row.len() as f32 - 1.0;
+    for (x, out) in output.iter_mut().enumerate() {
+        let src = (x as f32 - pixels).clamp(0.0, last);
+        let x0 = src.floor() as usize;
+        let x1 = (x0 + 1).min(row.len() - 1);
+        let t = src - x0 as f32;
+        *out = row[x0] * (1.0 - t) + row[x1] * t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// Run the pass over every row of `source`
+    fn apply(source: &RgbaImage, config: &CompositeConfig, level: f32, output: &mut RgbaImage) {
+        let width = source.width() as usize;
+        for (y, row) in output.chunks_exact_mut(width * 4).enumerate() {
+            apply_row(source, config, level, y as u32, row);
+        }
+    }
+
+    #[test]
+    fn test_yiq_round_trip() {
+        for rgb in [[0.0, 0.0, 0.0], [1.0, 1.0, 1.0], [0.8, 0.2, 0.4]] {
+            let back = yiq_to_rgb(rgb_to_yiq(Srgb::new(rgb[0], rgb[1], rgb[2])));
+            assert!((back.red - rgb[0]).abs() < 0.01);
+            assert!((back.green - rgb[1]).abs() < 0.01);
+            assert!((back.blue - rgb[2]).abs() < 0.01);
+        }
+    }
+
+    #[test]
+    fn test_neutral_config_keeps_image() {
+        let neutral = CompositeConfig {
+            chroma_blur: 0,
+            chroma_bleed: 0.0,
+            dot_crawl: 0.0,
+            ringing: 0.0,
+        };
+        let source =
+            RgbaImage::from_fn(8, 4, |x, y| Rgba([(x * 30) as u8, (y * 60) as u8, 90, 255]));
+
+        let mut output = RgbaImage::new(8, 4);
+        apply(&source, &neutral, 0.5, &mut output);
+        for (a, b) in source.pixels().zip(output.pixels()) {
+            for c in 0..4 {
+                assert!((a[c] as i32 - b[c] as i32).abs() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chroma_bleeds_to_the_right() {
+        let config = CompositeConfig {
+            dot_crawl: 0.0,
+            ringing: 0.0,
+            ..CompositeConfig::default()
+        };
+        // Red bar on grey, right of which the colour should smear
+        let source = RgbaImage::from_fn(16, 1, |x, _| {
+            if x < 8 {
+                Rgba([220, 40, 40, 255])
+            } else {
+                Rgba([128, 128, 128, 255])
+            }
+        });
+
+        let mut output = RgbaImage::new(16, 1);
+        apply(&source, &config, 0.0, &mut output);
+        let right = output.get_pixel(9, 0);
+        assert!(right[0] > right[2]);
+    }
+}
@@ -0,0 +1,108 @@
+//! Configuration files
+//!
+//! With the `serde` feature, [`ElectronBeamConfig`] round-trips through TOML
+//! and JSON so effect settings can live in version-controlled files. Keys
+//! missing from a file keep their default values.
+
+use crate::{ElectronBeamConfig, ElectronBeamError, Result};
+
+impl ElectronBeamConfig {
+    /// Parse a configuration from TOML
+    pub fn from_toml(text: &str) -> Result<Self> {
+        toml::from_str(text).map_err(|err| ElectronBeamError::ConfigFormat {
+            format: "TOML",
+            message: err.to_string(),
+        })
+    }
+
+    /// Serialise the configuration as TOML
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|err| ElectronBeamError::ConfigFormat {
+            format: "TOML",
+            message: err.to_string(),
+        })
+    }
+
+    /// Parse a configuration from JSON
+    pub fn from_json(text: &str) -> Result<Self> {
+        serde_json::from_str(text).map_err(|err| ElectronBeamError::ConfigFormat {
+            format: "JSON",
+            message: err.to_string(),
+        })
+    }
+
+    /// Serialise the configuration as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|err| ElectronBeamError::ConfigFormat {
+            format: "JSON",
+            message: err.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Animated, AnimationMode, BeamColor, CompositeConfig, ElectronBeamConfig, Phosphor,
+        TubeResolution,
+    };
+
+    fn custom_config() -> ElectronBeamConfig {
+        ElectronBeamConfig {
+            mode: AnimationMode::WarmUp,
+            v_stretch_duration: 0.3,
+            h_stretch_duration: 0.7,
+            tube: Some(TubeResolution {
+                scanline_intensity: 0.5,
+                ..TubeResolution::new(320, 240)
+            }),
+            composite: Some(CompositeConfig::default()),
+            phosphor: Some(Phosphor::Amber),
+            beam_color: BeamColor::Kelvin(6500.0),
+            beam_thickness: Some(0.01),
+            chroma_split: Animated::keyframes([(0.0, 0.0), (0.8, 0.5), (1.0, 2.0)]),
+            background: [10, 0, 20].into(),
+            timeline: Some(
+                "hold:0.1,vstretch:0.3,hstretch:0.6:ease-out"
+                    .parse()
+                    .unwrap(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = custom_config();
+
+        let toml = ElectronBeamConfig::from_toml(&config.to_toml().unwrap()).unwrap();
+        let json = ElectronBeamConfig::from_json(&config.to_json().unwrap()).unwrap();
+        for parsed in [toml, json] {
+            assert_eq!(format!("{parsed:?}"), format!("{config:?}"));
+        }
+    }
+
+    #[test]
+    fn test_partial_file_keeps_defaults() {
+        let config = ElectronBeamConfig::from_toml(
+            r#"
+            mode = "scale-down"
+            beam_color = { rgb = [255, 0, 0] }
+
+            [tube]
+            width = 320
+            height = 240
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.mode, AnimationMode::ScaleDown);
+        assert_eq!(config.beam_color, BeamColor::Rgb([255, 0, 0]));
+        assert_eq!(config.tube, Some(TubeResolution::new(320, 240)));
+        assert_eq!(config.width, 640);
+        assert_eq!(config.v_stretch_duration, 0.5);
+
+        let error = ElectronBeamConfig::from_json(r#"{ "mode": "sideways" }"#).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid JSON configuration"));
+    }
+}
@@ -0,0 +1,329 @@
+//! Renderer-agnostic draw lists
+//!
+//! The Java original draws every frame as a few textured or solid quads with
+//! colour masks and blend functions. A [`DrawList`] describes a frame the same
+//! way, so a GPU application, game engine or canvas can replay the effect
+//! natively instead of uploading frames rendered on the CPU.
+
+use crate::{AnimationMode, ElectronBeam, ElectronBeamError, Phase, PreparedBeam, Quad, Result};
+
+/// How a command combines with what is already in the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BlendFunc {
+    /// Overwrite the destination (blending disabled)
+    Replace,
+    /// `GL_ONE, GL_ONE`: add to the destination, saturating
+    Additive,
+}
+
+/// Channels a command writes, like `glColorMask`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ColorMask {
+    pub red: bool,
+    pub green: bool,
+    pub blue: bool,
+    pub alpha: bool,
+}
+
+impl ColorMask {
+    /// Every channel
+    pub const ALL: Self = Self::new(true, true, true, true);
+
+    pub const fn new(red: bool, green: bool, blue: bool, alpha: bool) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha,
+        }
+    }
+
+    /// One colour channel (0 = red, 1 = green, 2 = blue) plus alpha
+    pub const fn channel(channel: usize) -> Self {
+        Self::new(channel == 0, channel == 1, channel == 2, true)
+    }
+}
+
+/// What a command draws inside its quad
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Fill {
+    /// The source texture, the `uv` rectangle of which is stretched over the
+    /// quad, multiplied by `tint` (RGBA, 0.0 to 1.0)
+    Texture { uv: Quad, tint: [f32; 4] },
+    /// A solid RGBA colour (0.0 to 1.0) over the whole quad
+    Solid([f32; 4]),
+    /// A solid RGBA colour in the ellipse inscribed in the quad
+    Ellipse([f32; 4]),
+}
+
+/// One quad of a draw list
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DrawCommand {
+    /// Position in render pixels, origin at the top left, y pointing down
+    pub quad: Quad,
+    pub fill: Fill,
+    pub mask: ColorMask,
+    pub blend: BlendFunc,
+}
+
+/// The commands drawing one frame, in order
+///
+/// Coordinates are in render pixels: the output size, or the tube
+/// resolution when one is set, in which case the caller upscales the frame.
+/// Textures sample the prepared source; composite artifacts are not part of
+/// the list.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DrawList {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA colour the frame is cleared to before the commands
+    pub clear: [f32; 4],
+    /// Monochrome phosphor colour; texture samples are shown as their luma
+    /// (Rec. 601) times this colour when set
+    pub phosphor: Option<[f32; 3]>,
+    pub commands: Vec<DrawCommand>,
+}
+
+/// The whole texture
+const FULL_UV: Quad = Quad {
+    x: 0.0,
+    y: 0.0,
+    w: 1.0,
+    h: 1.0,
+};
+
+/// Convert an 8-bit colour to normalised RGBA
+fn rgba([r, g, b]: [u8; 3], alpha: f32) -> [f32; 4] {
+    [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, alpha]
+}
+
+impl ElectronBeam {
+    /// Describe the frame at `level` (0.0 to 1.0) as a list of quads
+    ///
+    /// Replaying the list reproduces [`draw`](Self::draw) up to sampling
+    /// differences. Fails for custom timeline phases, which are drawn by
+    /// arbitrary code.
+    pub fn draw_list(&self, level: f32) -> Result<DrawList> {
+        let state = self.phase_at(level)?;
+        let params = self.params_at(level);
+        let (width, height) = self.render_size();
+        let frame = Quad {
+            x: 0.0,
+            y: 0.0,
+            w: width as f32,
+            h: height as f32,
+        };
+        let background = rgba(params.background, 1.0);
+        let textured = |quad, tint| DrawCommand {
+            quad,
+            fill: Fill::Texture { uv: FULL_UV, tint },
+            mask: ColorMask::ALL,
+            blend: BlendFunc::Replace,
+        };
+
+        let (clear, commands) = match &state.phase {
+            Phase::Hold => (background, vec![textured(frame, [1.0; 4])]),
+            Phase::VStretch => {
+                // Each channel plane is added on its own, like glColorMask
+                let mut commands: Vec<_> = (0..3)
+                    .map(|channel| DrawCommand {
+                        mask: ColorMask::channel(channel),
+                        blend: BlendFunc::Additive,
+                        ..textured(state.quads[channel], [1.0; 4])
+                    })
+                    .collect();
+                if self.config.mode == AnimationMode::CoolDown {
+                    let highlight = self.highlight_color(&params, state.curves[1]);
+                    commands.push(DrawCommand {
+                        quad: frame,
+                        fill: Fill::Solid(rgba(highlight, 0.0)),
+                        mask: ColorMask::new(true, true, true, false),
+                        blend: BlendFunc::Additive,
+                    });
+                }
+                ([background[0], background[1], background[2], 0.0], commands)
+            }
+            Phase::HStretch => {
+                // The line covers whole pixels, as drawn on the CPU
+                let commands = self
+                    .h_stretch_line(state.progress)
+                    .map(|(quad, color)| DrawCommand {
+                        quad,
+                        fill: Fill::Solid(rgba(color, 1.0)),
+                        mask: ColorMask::ALL,
+                        blend: BlendFunc::Replace,
+                    })
+                    .into_iter()
+                    .collect();
+                (background, commands)
+            }
+            Phase::Dot => {
+                let fade = 1.0 - state.progress;
+                let color = rgba(self.beam_color(), 0.0).map(|c| c * fade);
+                let dot = DrawCommand {
+                    quad: state.quads[1],
+                    fill: Fill::Ellipse(color),
+                    mask: ColorMask::new(true, true, true, false),
+                    blend: BlendFunc::Additive,
+                };
+                (background, vec![dot])
+            }
+            Phase::Fade => {
+                let alpha = if self.config.mode == AnimationMode::WarmUp {
+                    state.progress
+                } else {
+                    1.0 - state.progress
+                };
+                ([0.0; 4], vec![textured(frame, [1.0, 1.0, 1.0, alpha])])
+            }
+            Phase::Scale => {
+                let curved_scale = state.curves[1];
+                let (_, scale) = self.scale_down_curve(state.progress);
+                let dim = if self.config.mode == AnimationMode::WarmUp {
+                    scale
+                } else {
+                    scale * (1.0 - curved_scale * 0.5)
+                };
+                let quad = state.quads[1];
+                let commands = (quad.w > 0.0 && quad.h > 0.0)
+                    .then(|| textured(quad, [dim, dim, dim, 1.0]))
+                    .into_iter()
+                    .collect();
+                (background, commands)
+            }
+            Phase::Custom { name, .. } => {
+                return Err(ElectronBeamError::UnsupportedPhase(name.clone()))
+            }
+        };
+
+        Ok(DrawList {
+            width,
+            height,
+            clear,
+            phosphor: self
+                .config
+                .phosphor
+                .map(|phosphor| phosphor.color().map(|c| c as f32 / 255.0)),
+            commands,
+        })
+    }
+}
+
+impl PreparedBeam {
+    /// Describe the frame at `level` as a list of quads, see [`ElectronBeam::draw_list`]
+    pub fn draw_list(&self, level: f32) -> Result<DrawList> {
+        self.as_beam().draw_list(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElectronBeamBuilder, Timeline};
+    use image::{Rgba, RgbaImage};
+
+    /// Replay a draw list on the CPU with nearest sampling, the way the
+    /// renderer maps pixels into quads
+    fn replay(list: &DrawList, source: &RgbaImage) -> RgbaImage {
+        let to_u8 = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+        let mut frame = RgbaImage::from_pixel(list.width, list.height, Rgba(list.clear.map(to_u8)));
+
+        for command in &list.commands {
+            let quad = command.quad;
+            for (x, y, pixel) in frame.enumerate_pixels_mut() {
+                let (xf, yf) = (x as f32, y as f32);
+                if xf < quad.x || xf >= quad.x + quad.w || yf < quad.y || yf >= quad.y + quad.h {
+                    continue;
+                }
+                let color = match command.fill {
+                    Fill::Texture { uv, tint } => {
+                        let u = uv.x + (xf - quad.x) / quad.w * uv.w;
+                        let v = uv.y + (yf - quad.y) / quad.h * uv.h;
+                        let sx =
+                            (u * source.width() as f32).clamp(0.0, source.width() as f32 - 1.0);
+                        let sy =
+                            (v * source.height() as f32).clamp(0.0, source.height() as f32 - 1.0);
+                        let texel = source.get_pixel(sx as u32, sy as u32);
+                        std::array::from_fn(|i| texel[i] as f32 * tint[i])
+                    }
+                    Fill::Solid(color) | Fill::Ellipse(color) => color.map(|c| c * 255.0),
+                };
+                let mask = [
+                    command.mask.red,
+                    command.mask.green,
+                    command.mask.blue,
+                    command.mask.alpha,
+                ];
+                for i in 0..4 {
+                    if !mask[i] {
+                        continue;
+                    }
+                    pixel[i] = match command.blend {
+                        BlendFunc::Replace => color[i] as u8,
+                        BlendFunc::Additive => (pixel[i] as f32 + color[i]).min(255.0) as u8,
+                    };
+                }
+            }
+        }
+        frame
+    }
+
+    fn source() -> RgbaImage {
+        RgbaImage::from_fn(48, 36, |x, y| Rgba([(x * 5) as u8, (y * 7) as u8, 90, 255]))
+    }
+
+    #[test]
+    fn test_replay_matches_draw() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(48, 36)
+            .stretch_durations(0.4, 0.6)
+            .highlight([255, 255, 255], 0.5)
+            .prepare(source())
+            .unwrap();
+
+        for level in [0.0, 0.1, 0.25, 0.39, 0.5, 0.8, 1.0] {
+            let list = beam.draw_list(level).unwrap();
+            let expected = beam.draw(level).unwrap();
+            let replayed = replay(&list, &source());
+            let differing = expected
+                .pixels()
+                .zip(replayed.pixels())
+                .filter(|(a, b)| (0..4).any(|i| a[i].abs_diff(b[i]) > 1))
+                .count();
+            assert_eq!(differing, 0, "level {level}");
+        }
+    }
+
+    #[test]
+    fn test_draw_list_commands() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(48, 36)
+            .build()
+            .unwrap();
+
+        let v = beam.draw_list(0.2).unwrap();
+        let masks: Vec<_> = v.commands.iter().map(|c| c.mask).collect();
+        assert_eq!(masks[..3], [0, 1, 2].map(ColorMask::channel));
+        assert!(v.commands.iter().all(|c| c.blend == BlendFunc::Additive));
+        assert!(matches!(v.commands[3].fill, Fill::Solid(_)));
+
+        assert_eq!(beam.draw_list(1.0).unwrap().commands, []);
+
+        let custom = Phase::custom("noop", |_, _, _| {});
+        let beam = ElectronBeamBuilder::new()
+            .timeline(Timeline::new().then(custom, 1.0))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            beam.draw_list(0.5),
+            Err(ElectronBeamError::UnsupportedPhase(name)) if name == "noop"
+        ));
+    }
+}
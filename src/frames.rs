@@ -0,0 +1,484 @@
+//! Lazy frame sequences
+//!
+//! Frames are rendered one at a time as the iterator is advanced, so an
+//! encoder consuming the sequence only ever holds the frame it is writing.
+//! [`Frames::render_parallel`] instead renders several frames at once while a
+//! writer thread consumes them in order.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::ops::Range;
+use std::sync::{mpsc, Condvar, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use image::RgbaImage;
+
+use crate::{ElectronBeam, ElectronBeamError, Result};
+
+/// Delay between frames when none is given, matching the CLI default
+pub const DEFAULT_FRAME_DELAY: Duration = Duration::from_millis(100);
+
+/// A rendered frame of the animation with its timing
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    /// Position of the frame in the sequence, starting at 0
+    pub index: u32,
+    /// Animation level the frame was drawn at
+    pub level: f32,
+    /// Time from the start of the animation at which the frame is shown
+    pub timestamp: Duration,
+    /// How long the frame stays on screen
+    pub delay: Duration,
+    /// The rendered frame
+    pub image: RgbaImage,
+}
+
+/// Iterator rendering the frames of an animation on demand
+///
+/// Created by [`ElectronBeam::frames`] or [`ElectronBeam::frames_at`]. Levels
+/// are spread evenly from 0.0 to 1.0, first and last frame included.
+#[derive(Clone)]
+pub struct Frames<'a> {
+    beam: &'a ElectronBeam,
+    count: u32,
+    next: u32,
+    delay: Duration,
+    reverse: bool,
+}
+
+impl<'a> Frames<'a> {
+    pub(crate) fn new(beam: &'a ElectronBeam, count: u32, delay: Duration) -> Self {
+        Self {
+            beam,
+            count,
+            next: 0,
+            delay,
+            reverse: false,
+        }
+    }
+
+    /// Set how long each frame stays on screen
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Play the animation backwards, from level 1.0 down to 0.0
+    pub fn reversed(mut self) -> Self {
+        self.reverse = !self.reverse;
+        self
+    }
+
+    /// Level of the frame at `index` in the sequence
+    pub fn level_at(&self, index: u32) -> f32 {
+        let level = if self.count > 1 {
+            index as f32 / (self.count - 1) as f32
+        } else {
+            0.0
+        };
+
+        if self.reverse {
+            1.0 - level
+        } else {
+            level
+        }
+    }
+
+    /// Total playing time of the sequence
+    pub fn duration(&self) -> Duration {
+        self.delay * self.count
+    }
+
+    /// Render the remaining frames concurrently and hand them to `sink` in order
+    ///
+    /// Frames are rendered on the beam's thread pool (rayon's global pool
+    /// when it has none) while `sink` runs on a dedicated writer thread, so
+    /// encoding overlaps rendering. A beam with one thread renders one frame
+    /// at a time on the calling thread. Frames finishing early wait in a
+    /// reorder buffer and are passed on as soon as every frame before them
+    /// has been, so the writer receives them strictly in sequence order. A
+    /// frame is only started when it is less than `window` frames ahead of
+    /// the last one written, which bounds the frames held in memory. A
+    /// `window` of 0 allows one frame per render thread.
+    ///
+    /// Stops at the first error and returns it. Errors from `sink` are
+    /// reported as [`ElectronBeamError::Callback`].
+    pub fn render_parallel<F>(self, window: usize, mut sink: F) -> Result<()>
+    where
+        F: FnMut(AnimationFrame) -> Result<(), Box<dyn Error + Send + Sync>> + Send,
+    {
+        let workers = match (self.beam.config.threads, &self.beam.pool) {
+            (1, _) => Workers::Calling,
+            (_, pool) => Workers::Pool(pool.as_deref()),
+        };
+        let window = if window == 0 {
+            workers.threads()
+        } else {
+            window
+        };
+
+        render_ordered(
+            self.next..self.count,
+            window,
+            workers,
+            |index| self.render_frame(index),
+            move |frame| {
+                let index = frame.index;
+                sink(frame).map_err(|err| ElectronBeamError::Callback {
+                    frame: index,
+                    source: err,
+                })
+            },
+        )
+    }
+
+    /// Render the frame at `index` in the sequence
+    fn render_frame(&self, index: u32) -> Result<AnimationFrame> {
+        let level = self.level_at(index);
+        self.beam
+            .draw(level)
+            .map_err(|err| ElectronBeamError::Frame {
+                frame: index,
+                source: Box::new(err),
+            })
+            .map(|image| AnimationFrame {
+                index,
+                level,
+                timestamp: self.delay * index,
+                delay: self.delay,
+                image,
+            })
+    }
+}
+
+/// Threads rendering the frames of [`render_ordered`]
+#[derive(Clone, Copy)]
+enum Workers<'a> {
+    /// The calling thread, one frame at a time
+    Calling,
+    /// A rayon thread pool, the global one when `None`
+    Pool(Option<&'a rayon::ThreadPool>),
+}
+
+impl Workers<'_> {
+    /// Number of frames that can render at once
+    fn threads(self) -> usize {
+        match self {
+            Workers::Calling => 1,
+            Workers::Pool(Some(pool)) => pool.current_num_threads(),
+            Workers::Pool(None) => rayon::current_num_threads(),
+        }
+    }
+}
+
+/// Progress shared by the writer and the renderer of [`render_ordered`]
+struct Schedule {
+    /// Indices below this have been passed to the sink
+    written: u32,
+    /// Set when the writer stops, on error or after the last frame
+    stop: bool,
+}
+
+/// Render `indices` with `workers`, at most `window` ahead of the writer,
+/// and pass the results to `sink` in order on a writer thread, stopping at
+/// the first error
+fn render_ordered<T, R, S>(
+    indices: Range<u32>,
+    window: usize,
+    workers: Workers,
+    render: R,
+    mut sink: S,
+) -> Result<()>
+where
+    T: Send,
+    R: Fn(u32) -> Result<T> + Sync,
+    S: FnMut(T) -> Result<()> + Send,
+{
+    let window = window.max(1);
+    let schedule = Mutex::new(Schedule {
+        written: indices.start,
+        stop: false,
+    });
+    let progress = Condvar::new();
+    // No more than `window` frames are ever waiting for the writer
+    let (sender, receiver) = mpsc::sync_channel::<(u32, Result<T>)>(window);
+
+    thread::scope(|scope| {
+        let (schedule, progress, render) = (&schedule, &progress, &render);
+        let writer = scope.spawn(move || {
+            let mut pending = BTreeMap::new();
+            let mut expected = indices.start;
+            let written = receiver.iter().try_for_each(|(index, frame)| {
+                pending.insert(index, frame);
+                while let Some(frame) = pending.remove(&expected) {
+                    sink(frame?)?;
+                    expected += 1;
+                    schedule.lock().unwrap().written = expected;
+                    progress.notify_all();
+                }
+                Ok(())
+            });
+
+            // Release the renderer if it is waiting for the window to move
+            schedule.lock().unwrap().stop = true;
+            progress.notify_all();
+            written
+        });
+
+        // A closed channel means the writer stopped on an error
+        let render_into = |index, sender: &mpsc::SyncSender<_>| {
+            let _ = sender.send((index, render(index)));
+        };
+        let dispatch = |spawn: &dyn Fn(u32)| {
+            for index in indices.clone() {
+                let mut state = schedule.lock().unwrap();
+                // Wait for the window to move past the frames in flight
+                while !state.stop && index - state.written >= window as u32 {
+                    state = progress.wait(state).unwrap();
+                }
+                if state.stop {
+                    return;
+                }
+                drop(state);
+                spawn(index);
+            }
+        };
+        match workers {
+            Workers::Calling => dispatch(&|index| render_into(index, &sender)),
+            // The calling thread waits for the window, only frames run on the pool
+            Workers::Pool(Some(pool)) => pool.in_place_scope(|tasks| {
+                dispatch(&|index| {
+                    let sender = sender.clone();
+                    tasks.spawn(move |_| render_into(index, &sender));
+                })
+            }),
+            Workers::Pool(None) => rayon::in_place_scope(|tasks| {
+                dispatch(&|index| {
+                    let sender = sender.clone();
+                    tasks.spawn(move |_| render_into(index, &sender));
+                })
+            }),
+        }
+        drop(sender);
+
+        writer
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    })
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Result<AnimationFrame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.count {
+            return None;
+        }
+
+        let index = self.next;
+        self.next += 1;
+        Some(self.render_frame(index))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.count - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Frames<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_ordered, Workers};
+    use crate::{AnimationMode, ElectronBeamBuilder, ElectronBeamError};
+    use image::{ImageBuffer, Rgba};
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::{mpsc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_frames_timing() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(8, 8)
+            .mode(AnimationMode::Fade)
+            .build()
+            .unwrap();
+        beam.prepare(ImageBuffer::from_pixel(8, 8, Rgba([255, 255, 255, 255])))
+            .unwrap();
+
+        let frames = beam.frames(5).delay(Duration::from_millis(40));
+        assert_eq!(frames.len(), 5);
+        assert_eq!(frames.duration(), Duration::from_millis(200));
+
+        let frames: Vec<_> = frames.map(Result::unwrap).collect();
+        let levels: Vec<_> = frames.iter().map(|f| f.level).collect();
+        assert_eq!(levels, [0.0, 0.25, 0.5, 0.75, 1.0]);
+        assert_eq!(frames[3].timestamp, Duration::from_millis(120));
+        assert_eq!(frames[3].delay, Duration::from_millis(40));
+        assert_eq!(frames[4].image, beam.draw(1.0).unwrap());
+    }
+
+    #[test]
+    fn test_frames_at_fps() {
+        let mut beam = ElectronBeamBuilder::new().dimensions(8, 8).build().unwrap();
+        beam.prepare(ImageBuffer::new(8, 8)).unwrap();
+
+        let frames = beam.frames_at(25.0, Duration::from_secs(2));
+        assert_eq!(frames.len(), 50);
+        assert_eq!(frames.duration(), Duration::from_secs(2));
+
+        let reversed = beam.frames(3).reversed();
+        assert_eq!(reversed.level_at(0), 1.0);
+        assert_eq!(reversed.level_at(2), 0.0);
+
+        // A single frame shows the start of the animation instead of dividing by zero
+        assert_eq!(beam.frames(1).level_at(0), 0.0);
+    }
+
+    #[test]
+    fn test_render_parallel_keeps_order() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(16, 12)
+            .build()
+            .unwrap();
+        beam.prepare(ImageBuffer::from_fn(16, 12, |x, y| {
+            Rgba([(x * 16) as u8, (y * 20) as u8, 128, 255])
+        }))
+        .unwrap();
+
+        let sequential: Vec<_> = beam.frames(17).map(Result::unwrap).collect();
+        for window in [0, 1, 4, 32] {
+            let mut parallel = Vec::new();
+            beam.frames(17)
+                .render_parallel(window, |frame| {
+                    parallel.push(frame);
+                    Ok(())
+                })
+                .unwrap();
+
+            assert_eq!(parallel.len(), sequential.len());
+            for (a, b) in parallel.iter().zip(&sequential) {
+                assert_eq!(
+                    (a.index, a.level, a.timestamp),
+                    (b.index, b.level, b.timestamp)
+                );
+                assert_eq!(a.image, b.image);
+            }
+        }
+    }
+
+    #[test]
+    fn test_render_parallel_stops_on_writer_error() {
+        let mut beam = ElectronBeamBuilder::new().dimensions(8, 8).build().unwrap();
+        beam.prepare(ImageBuffer::new(8, 8)).unwrap();
+
+        let mut written = 0;
+        let result = beam.frames(50).render_parallel(2, |frame| {
+            if frame.index == 3 {
+                return Err("disk full".into());
+            }
+            written += 1;
+            Ok(())
+        });
+
+        match result.unwrap_err() {
+            ElectronBeamError::Callback { frame, source } => {
+                assert_eq!(frame, 3);
+                assert_eq!(source.to_string(), "disk full");
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+        assert_eq!(written, 3);
+    }
+
+    #[test]
+    fn test_slow_frame_does_not_hold_back_the_window() {
+        // Frame 1 only finishes once frame 4 has started, which rendering in
+        // batches of four would never allow
+        let (started, wait) = mpsc::channel();
+        let wait = Mutex::new(wait);
+        let rendered = AtomicU32::new(0);
+        let mut written = Vec::new();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        render_ordered(
+            0..12,
+            4,
+            Workers::Pool(Some(&pool)),
+            |index| {
+                rendered.fetch_add(1, Ordering::SeqCst);
+                if index == 4 {
+                    started.send(()).unwrap();
+                }
+                if index == 1 {
+                    let wait = wait.lock().unwrap();
+                    wait.recv_timeout(Duration::from_secs(10))
+                        .expect("frame 4 should start while frame 1 renders");
+                }
+                Ok(index)
+            },
+            |index| {
+                // Nothing starts more than the window ahead of the writer
+                assert!(rendered.load(Ordering::SeqCst) <= written.len() as u32 + 4);
+                written.push(index);
+                Ok(())
+            },
+        )
+        .unwrap();
+
+        assert_eq!(written, (0..12).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_frames_render_on_the_beam_pool() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(16, 12)
+            .threads(2)
+            .build()
+            .unwrap();
+        beam.prepare(ImageBuffer::new(16, 12)).unwrap();
+        let pool = beam.pool.as_deref().unwrap();
+        let caller = thread::current().id();
+
+        // Frames render on the pool and are written on a thread of their own
+        let mut writers = Vec::new();
+        render_ordered(
+            0..8,
+            4,
+            Workers::Pool(Some(pool)),
+            |index| {
+                assert!(pool.current_thread_index().is_some());
+                beam.draw(index as f32 / 7.0)
+            },
+            |_| {
+                writers.push(thread::current().id());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(writers.len(), 8);
+        assert!(writers.iter().all(|&writer| writer != caller));
+
+        // Serial beams render on the calling thread
+        let mut rendered = Vec::new();
+        let record = Mutex::new(&mut rendered);
+        render_ordered(
+            0..3,
+            2,
+            Workers::Calling,
+            |index| {
+                record.lock().unwrap().push(thread::current().id());
+                Ok(index)
+            },
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(rendered, [caller; 3]);
+    }
+}
@@ -0,0 +1,201 @@
+//! Timeline introspection
+//!
+//! Reports what [`ElectronBeam::draw`] does at a level without drawing it:
+//! the active phase, the progress through it, the curve values it feeds the
+//! renderer and the quads it covers.
+
+use crate::{ElectronBeam, ElectronBeamError, Phase, PreparedBeam, Quad, Result};
+
+/// State of the animation at one level, as computed by `draw`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhaseState {
+    /// Animation level the state was computed at
+    pub level: f32,
+    /// Index of the active segment in the timeline
+    pub segment: usize,
+    pub phase: Phase,
+    /// Eased progress through the phase, from 0.0 to 1.0
+    pub progress: f32,
+    /// Curve values of the red, green and blue channels
+    ///
+    /// The per-channel S-curves during the vertical stretch, the S-curve of
+    /// the line or the scale, and the progress for the other phases.
+    pub curves: [f32; 3],
+    /// Quads of the red, green and blue channels, in render pixels
+    ///
+    /// The collapsing channel planes during the vertical stretch, the beam
+    /// line in the whole pixels it is drawn over (empty once it has
+    /// collapsed), the bounding box of the dot, the scaled picture, or the
+    /// whole frame for the other phases.
+    pub quads: [Quad; 3],
+}
+
+impl ElectronBeam {
+    /// Describe the phase drawn at `level` (0.0 to 1.0)
+    ///
+    /// Needs no source image, so a timeline can be inspected before the
+    /// animation is prepared.
+    pub fn phase_at(&self, level: f32) -> Result<PhaseState> {
+        if !(0.0..=1.0).contains(&level) {
+            return Err(ElectronBeamError::InvalidLevel(level));
+        }
+
+        let (segment, progress) = self.timeline.locate(level);
+        let index = self
+            .timeline
+            .segments()
+            .iter()
+            .position(|s| std::ptr::eq(s, segment))
+            .expect("located segments belong to the timeline");
+        Ok(self.phase_state(level, index, progress))
+    }
+
+    /// State of a segment of the timeline at an eased progress through it
+    pub(crate) fn phase_state(&self, level: f32, index: usize, progress: f32) -> PhaseState {
+        let segment = &self.timeline.segments()[index];
+        let params = self.params_at(level);
+
+        let (width, height) = self.render_size();
+        let frame = Quad {
+            x: 0.0,
+            y: 0.0,
+            w: width as f32,
+            h: height as f32,
+        };
+        let (curves, quads) = match &segment.phase {
+            Phase::VStretch => {
+                let curves = self.v_stretch_curves(progress, &params);
+                (curves, curves.map(|a| self.v_stretch_quad(a)))
+            }
+            Phase::HStretch => {
+                let ag = self.scurve(progress, 8.0);
+                let line = match self.h_stretch_line(progress) {
+                    Some((line, _)) => line,
+                    None => Quad {
+                        w: 0.0,
+                        h: 0.0,
+                        ..self.h_stretch_quad(ag)
+                    },
+                };
+                ([ag; 3], [line; 3])
+            }
+            Phase::Dot => {
+                let (dw, dh) = self.display_size();
+                let radius = self.dot_radius(progress);
+                let bounds = self.display_to_storage(Quad {
+                    x: dw * 0.5 - radius,
+                    y: dh * 0.5 - radius,
+                    w: 2.0 * radius,
+                    h: 2.0 * radius,
+                });
+                ([progress; 3], [bounds; 3])
+            }
+            Phase::Scale => {
+                let (curved_scale, scale) = self.scale_down_curve(progress);
+                ([curved_scale; 3], [self.scale_down_quad(scale); 3])
+            }
+            Phase::Hold => ([0.0; 3], [frame; 3]),
+            Phase::Fade | Phase::Custom { .. } => ([progress; 3], [frame; 3]),
+        };
+
+        PhaseState {
+            level,
+            segment: index,
+            phase: segment.phase.clone(),
+            progress,
+            curves,
+            quads,
+        }
+    }
+}
+
+impl PreparedBeam {
+    /// Describe the phase drawn at `level`, see [`ElectronBeam::phase_at`]
+    pub fn phase_at(&self, level: f32) -> Result<PhaseState> {
+        self.as_beam().phase_at(level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElectronBeamBuilder, Timeline};
+
+    #[test]
+    fn test_phase_at_classic() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .stretch_durations(0.4, 0.6)
+            .build()
+            .unwrap();
+
+        let start = beam.phase_at(0.0).unwrap();
+        assert_eq!((start.segment, &start.phase), (0, &Phase::VStretch));
+        let quad = start.quads[1];
+        assert!((quad.w - 64.0).abs() < 1e-3 && (quad.h - 48.0).abs() < 1e-3);
+
+        // Red collapses ahead of green, and blue behind it
+        let v = beam.phase_at(0.1).unwrap();
+        assert_eq!(v.progress, 0.25);
+        assert!(v.curves[0] > v.curves[1] && v.curves[1] > v.curves[2]);
+        assert!(v.quads[0].h < v.quads[1].h && v.quads[1].h < v.quads[2].h);
+
+        let h = beam.phase_at(0.7).unwrap();
+        assert_eq!((h.segment, &h.phase), (1, &Phase::HStretch));
+        assert!((h.progress - 0.5).abs() < 1e-6);
+        assert_eq!(h.curves, [h.curves[0]; 3]);
+
+        // The line is reported where it is drawn
+        beam.prepare(image::RgbaImage::new(64, 48)).unwrap();
+        let frame = beam.draw(0.7).unwrap();
+        let lit: Vec<_> = frame
+            .enumerate_pixels()
+            .filter(|(_, _, pixel)| pixel[0] > 0)
+            .map(|(x, y, _)| (x as f32, y as f32))
+            .collect();
+        let (xs, ys): (Vec<_>, Vec<_>) = lit.iter().copied().unzip();
+        let min = |v: &[f32]| v.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = |v: &[f32]| v.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let drawn = Quad {
+            x: min(&xs),
+            y: min(&ys),
+            w: max(&xs) - min(&xs) + 1.0,
+            h: max(&ys) - min(&ys) + 1.0,
+        };
+        assert_eq!(h.quads, [drawn; 3]);
+        assert_eq!(lit.len() as f32, drawn.w * drawn.h);
+        assert_eq!(h.quads[0].h, 3.0);
+
+        let collapsed = beam.phase_at(1.0).unwrap();
+        assert_eq!((collapsed.quads[0].w, collapsed.quads[0].h), (0.0, 0.0));
+
+        assert!(beam.phase_at(1.5).is_err());
+    }
+
+    #[test]
+    fn test_phase_at_timeline() {
+        let timeline: Timeline = "hold:1,scale:1,dot:1".parse().unwrap();
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(100, 50)
+            .timeline(timeline)
+            .build()
+            .unwrap();
+
+        assert_eq!(beam.phase_at(0.2).unwrap().phase, Phase::Hold);
+        let scale = beam.phase_at(0.5).unwrap();
+        assert_eq!(
+            (scale.segment, scale.quads[0]),
+            (
+                1,
+                Quad {
+                    x: 25.0,
+                    y: 12.0,
+                    w: 50.0,
+                    h: 25.0
+                }
+            )
+        );
+        let dot = beam.phase_at(1.0).unwrap();
+        assert_eq!((dot.phase, dot.quads[0].w), (Phase::Dot, 0.0));
+    }
+}
@@ -4,25 +4,138 @@
 //! the appearance of an old television or monitor turning off, with the characteristic
 //! horizontal and vertical stretching and color separation effects.
 
-use anyhow::Result;
+mod animated;
+mod animator;
+mod composite;
+#[cfg(feature = "serde")]
+mod config_file;
+mod draw_list;
+mod frames;
+mod inspect;
+mod lifecycle;
+mod mip;
+mod prepared;
+mod presets;
+mod shader;
+mod simd;
+mod timeline;
+mod web;
 
-use image::{ImageBuffer, Rgba, RgbaImage};
+pub use animated::{Animated, Keyframe, Lerp};
+pub use animator::{Animator, Clock, ManualClock, SystemClock};
+pub use composite::CompositeConfig;
+pub use draw_list::{BlendFunc, ColorMask, DrawCommand, DrawList, Fill};
+pub use frames::{AnimationFrame, Frames, DEFAULT_FRAME_DELAY};
+pub use inspect::PhaseState;
+pub use lifecycle::ScreenStateAnimator;
+pub use prepared::PreparedBeam;
+pub use presets::{Preset, PRESETS};
+pub use shader::ShaderLanguage;
+pub use timeline::{Easing, Phase, PhaseFn, Segment, Timeline};
+pub use web::WebAnimation;
+
+use image::{ImageBuffer, RgbaImage};
+use mip::MipPyramid;
+use palette::convert::FromColorUnclamped;
+use palette::{LinSrgb, Srgb, Yxy};
+use rayon::prelude::*;
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::time::Duration;
+
+/// Result type of the library, failing with an [`ElectronBeamError`]
+pub type Result<T, E = ElectronBeamError> = std::result::Result<T, E>;
 
 /// Errors that can occur during ElectronBeam operations
+///
+/// New variants may be added, and some only exist with a crate feature, so
+/// matches need a wildcard arm.
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum ElectronBeamError {
     #[error("Invalid animation mode: {0}")]
     InvalidMode(String),
     #[error("Invalid level value: {0} (must be between 0.0 and 1.0)")]
     InvalidLevel(f32),
-    #[error("Image processing error: {0}")]
-    ImageError(String),
+    #[error("Source image is {width}x{height} (must be greater than 0x0)")]
+    EmptySourceImage { width: u32, height: u32 },
     #[error("Animation not prepared")]
     NotPrepared,
+    #[error("Failed to generate frame {}", .frame + 1)]
+    Frame {
+        frame: u32,
+        #[source]
+        source: Box<ElectronBeamError>,
+    },
+    #[error("Frame callback failed on frame {}", .frame + 1)]
+    Callback {
+        frame: u32,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error(
+        "Output is {actual_width}x{actual_height}, expected {expected_width}x{expected_height}"
+    )]
+    DimensionMismatch {
+        expected_width: u32,
+        expected_height: u32,
+        actual_width: u32,
+        actual_height: u32,
+    },
+    #[error("Row stride of {stride} bytes is shorter than a row of {min} bytes")]
+    InvalidStride { stride: usize, min: usize },
+    #[error("Output buffer holds {actual} bytes, {needed} are needed")]
+    BufferTooSmall { needed: usize, actual: usize },
+    #[error("Invalid output dimensions {width}x{height} (must be greater than 0x0)")]
+    InvalidDimensions { width: u32, height: u32 },
+    #[error("Invalid vertical stretch duration: {0} (must be at least 0.0 and below 1.0)")]
+    InvalidVStretchDuration(f32),
+    #[error("Invalid horizontal stretch duration: {0} (must be above 0.0 and at most 1.0)")]
+    InvalidHStretchDuration(f32),
+    #[error("Stretch durations {v_stretch} and {h_stretch} must add up to 1.0")]
+    StretchDurationSum { v_stretch: f32, h_stretch: f32 },
+    #[error("Invalid pixel aspect ratio: {0} (must be greater than 0.0)")]
+    InvalidPixelAspectRatio(f32),
+    #[error("Invalid tube resolution {width}x{height} (must be greater than 0x0)")]
+    InvalidTubeResolution { width: u32, height: u32 },
+    #[error("Invalid scanline intensity: {0} (must be between 0.0 and 1.0)")]
+    InvalidScanlineIntensity(f32),
+    #[error("Invalid composite {field}: {value} (must be 0.0 or more)")]
+    InvalidComposite { field: &'static str, value: f32 },
+    #[error("Invalid composite chroma blur: {0} pixels (must be at most {max})", max = composite::MAX_CHROMA_BLUR)]
+    InvalidChromaBlur(u32),
+    #[error("Invalid beam colour temperature: {0}K (must be greater than 0)")]
+    InvalidBeamKelvin(f32),
+    #[error("Invalid beam thickness: {0} (must be above 0.0 and at most 1.0)")]
+    InvalidBeamThickness(f32),
+    #[error("Invalid highlight strength: {0} (must be 0.0 or more)")]
+    InvalidHighlightStrength(f32),
+    #[error("Invalid chroma split: {0} (must be 0.0 or more)")]
+    InvalidChromaSplit(f32),
+    #[error("Keyframes of {0} must be in increasing level order between 0.0 and 1.0")]
+    InvalidKeyframes(&'static str),
+    #[error("Unknown preset: {0}")]
+    UnknownPreset(String),
+    #[error("Invalid timeline segment: {0:?} (expected phase:duration[:easing])")]
+    InvalidTimelineSpec(String),
+    #[error("Invalid phase duration: {0} (must be 0.0 or more)")]
+    InvalidPhaseDuration(f32),
+    #[error("Timeline has no phase with a duration above 0.0")]
+    EmptyTimeline,
+    #[error("Custom phase {0} cannot be exported")]
+    UnsupportedPhase(String),
+    #[cfg(feature = "serde")]
+    #[error("Invalid {format} configuration: {message}")]
+    ConfigFormat {
+        format: &'static str,
+        message: String,
+    },
 }
 
 /// Animation modes for the ElectronBeam effect
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
 pub enum AnimationMode {
     /// Animates an electron beam warming up (turning on)
     WarmUp,
@@ -34,8 +147,37 @@ pub enum AnimationMode {
     ScaleDown,
 }
 
+impl std::str::FromStr for AnimationMode {
+    type Err = ElectronBeamError;
+
+    /// Parse a mode by its kebab-case name, e.g. `cool-down`
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "warm-up" => Ok(AnimationMode::WarmUp),
+            "cool-down" => Ok(AnimationMode::CoolDown),
+            "fade" => Ok(AnimationMode::Fade),
+            "scale-down" => Ok(AnimationMode::ScaleDown),
+            _ => Err(ElectronBeamError::InvalidMode(name.to_string())),
+        }
+    }
+}
+
+/// Resampling quality of the scale down effect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum ScaleQuality {
+    /// Trilinear sampling from a mip pyramid built once in `prepare`
+    #[default]
+    Trilinear,
+    /// Lanczos3 resize of the full source on every frame (slow, reference quality)
+    ExactLanczos,
+}
+
 /// Configuration for the ElectronBeam animation
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ElectronBeamConfig {
     /// Width of the output frames
     pub width: u32,
@@ -47,6 +189,170 @@ pub struct ElectronBeamConfig {
     pub v_stretch_duration: f32,
     /// Duration of the horizontal stretch effect (0.0 to 1.0) - happens second
     pub h_stretch_duration: f32,
+    /// Pixel aspect ratio (displayed width / height of a single pixel), 1.0 for square pixels
+    ///
+    /// Use e.g. 0.889 for 720x480 or 0.833 for 320x200 content shown at 4:3.
+    pub pixel_aspect_ratio: f32,
+    /// Optional low-resolution tube to render at before upscaling to the output size
+    pub tube: Option<TubeResolution>,
+    /// Optional composite video artifacts applied to the source before the collapse
+    pub composite: Option<CompositeConfig>,
+    /// Optional monochrome phosphor; the picture is shown in its colour only
+    pub phosphor: Option<Phosphor>,
+    /// Colour of the collapsing beam line (ignored when a phosphor is set)
+    pub beam_color: BeamColor,
+    /// Thickness of the beam line as a fraction of the output height
+    ///
+    /// `None` keeps the classic line of two pixels.
+    pub beam_thickness: Option<f32>,
+    /// Colour added over the picture as it collapses in cool down mode
+    /// (ignored when a phosphor is set)
+    pub highlight_tint: Animated<[u8; 3]>,
+    /// Strength of the highlight (0.0 = none, 1.0 = classic)
+    pub highlight_strength: Animated<f32>,
+    /// Separation of the colour channels during the vertical stretch
+    /// (0.0 = none, 1.0 = classic)
+    pub chroma_split: Animated<f32>,
+    /// Colour behind the collapsing picture, beam line and dot
+    pub background: Animated<[u8; 3]>,
+    /// Number of threads used to render the rows of a frame
+    ///
+    /// 0 uses rayon's global thread pool, 1 renders serially.
+    pub threads: usize,
+    /// Resampling quality of the scale down effect
+    pub scale_quality: ScaleQuality,
+    /// Optional sequence of phases replacing the one of the mode
+    ///
+    /// The stretch durations are ignored when a timeline is set; the mode
+    /// still picks the highlight and the direction of fades and scales.
+    pub timeline: Option<Timeline>,
+}
+
+/// Colour of the electron beam line
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum BeamColor {
+    /// Explicit sRGB colour
+    Rgb([u8; 3]),
+    /// Colour of a black body at the given temperature in Kelvin (1667 to 25000)
+    Kelvin(f32),
+}
+
+impl Default for BeamColor {
+    fn default() -> Self {
+        BeamColor::Rgb([255, 255, 255])
+    }
+}
+
+impl BeamColor {
+    /// Resolve the colour to sRGB
+    ///
+    /// Temperatures follow the Planckian locus and are scaled so the brightest
+    /// channel is at full intensity.
+    pub fn to_rgb(self) -> [u8; 3] {
+        match self {
+            BeamColor::Rgb(rgb) => rgb,
+            BeamColor::Kelvin(kelvin) => {
+                let (x, y) = planckian_locus(kelvin.clamp(1667.0, 25000.0));
+                let linear = LinSrgb::from_color_unclamped(Yxy::new(x, y, 1.0));
+                let (r, g, b) = linear.into_components();
+                let max = r.max(g).max(b);
+                let normalized =
+                    LinSrgb::new((r / max).max(0.0), (g / max).max(0.0), (b / max).max(0.0));
+                let rgb: Srgb<u8> = Srgb::from_linear(normalized);
+                [rgb.red, rgb.green, rgb.blue]
+            }
+        }
+    }
+}
+
+/// CIE 1931 chromaticity of a black body, using the cubic spline
+/// approximation of Kim et al.
+fn planckian_locus(t: f32) -> (f32, f32) {
+    let t = t as f64;
+    let (t2, t3) = (t * t, t * t * t);
+    let x = if t <= 4000.0 {
+        -0.2661239e9 / t3 - 0.2343589e6 / t2 + 0.8776956e3 / t + 0.179910
+    } else {
+        -3.0258469e9 / t3 + 2.1070379e6 / t2 + 0.2226347e3 / t + 0.240390
+    };
+    let (x2, x3) = (x * x, x * x * x);
+    let y = if t <= 2222.0 {
+        -1.1063814 * x3 - 1.34811020 * x2 + 2.18555832 * x - 0.20219683
+    } else if t <= 4000.0 {
+        -0.9549476 * x3 - 1.37418593 * x2 + 2.09137015 * x - 0.16748867
+    } else {
+        3.0817580 * x3 - 5.87338670 * x2 + 3.75112997 * x - 0.37001483
+    };
+    (x as f32, y as f32)
+}
+
+/// Phosphor types of monochrome monitors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Phosphor {
+    /// P1 green, as on early terminals and oscilloscopes
+    Green,
+    /// P3 amber
+    Amber,
+    /// P4 white, with its slightly blue cast
+    White,
+}
+
+impl Phosphor {
+    /// The colour the phosphor glows at full intensity
+    pub fn color(self) -> [u8; 3] {
+        match self {
+            Phosphor::Green => [51, 255, 51],
+            Phosphor::Amber => [255, 176, 0],
+            Phosphor::White => [232, 238, 255],
+        }
+    }
+
+    /// Map RGBA pixels to luminance, tinted with the phosphor colour
+    fn tint(self, pixels: &mut [u8]) {
+        let color = self.color();
+        for pixel in pixels.chunks_exact_mut(4) {
+            let luma =
+                (0.299 * pixel[0] as f32 + 0.587 * pixel[1] as f32 + 0.114 * pixel[2] as f32)
+                    / 255.0;
+            for i in 0..3 {
+                pixel[i] = (color[i] as f32 * luma) as u8;
+            }
+        }
+    }
+}
+
+/// Virtual "tube resolution" for low-resolution CRT emulation
+///
+/// The effect is rendered at this resolution and then upscaled to the output
+/// size by the largest integer factor that fits, using nearest-neighbour
+/// sampling and centring the picture on a black border.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TubeResolution {
+    /// Width of the tube in pixels
+    pub width: u32,
+    /// Height of the tube in pixels
+    pub height: u32,
+    /// Darkening of the gap between scanlines (0.0 = none, 1.0 = black gaps)
+    ///
+    /// Only visible when the upscale factor is at least 2.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub scanline_intensity: f32,
+}
+
+impl TubeResolution {
+    /// Create a tube resolution without scanline darkening
+    pub fn new(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            scanline_intensity: 0.0,
+        }
+    }
 }
 
 impl Default for ElectronBeamConfig {
@@ -57,263 +363,959 @@ impl Default for ElectronBeamConfig {
             mode: AnimationMode::CoolDown,
             v_stretch_duration: 0.5,
             h_stretch_duration: 0.5,
+            pixel_aspect_ratio: 1.0,
+            tube: None,
+            composite: None,
+            phosphor: None,
+            beam_color: BeamColor::default(),
+            beam_thickness: None,
+            highlight_tint: [255, 255, 255].into(),
+            highlight_strength: 1.0.into(),
+            chroma_split: 1.0.into(),
+            background: [0, 0, 0].into(),
+            threads: 0,
+            scale_quality: ScaleQuality::Trilinear,
+            timeline: None,
         }
     }
 }
 
+/// Largest difference from 1.0 accepted for the sum of the stretch durations
+const STRETCH_SUM_TOLERANCE: f32 = 1e-3;
+
+impl ElectronBeamConfig {
+    /// Check that every setting is in range
+    ///
+    /// Called by [`ElectronBeam::new`] and [`ElectronBeamBuilder::build`], so
+    /// an animation can never be created from an invalid configuration.
+    pub fn validate(&self) -> Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(ElectronBeamError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        let (v_stretch, h_stretch) = (self.v_stretch_duration, self.h_stretch_duration);
+        if !(0.0..1.0).contains(&v_stretch) {
+            return Err(ElectronBeamError::InvalidVStretchDuration(v_stretch));
+        }
+        // The horizontal stretch divides by its duration
+        if !(h_stretch > 0.0 && h_stretch <= 1.0) {
+            return Err(ElectronBeamError::InvalidHStretchDuration(h_stretch));
+        }
+        if (v_stretch + h_stretch - 1.0).abs() > STRETCH_SUM_TOLERANCE {
+            return Err(ElectronBeamError::StretchDurationSum {
+                v_stretch,
+                h_stretch,
+            });
+        }
+
+        if !(self.pixel_aspect_ratio > 0.0 && self.pixel_aspect_ratio.is_finite()) {
+            return Err(ElectronBeamError::InvalidPixelAspectRatio(
+                self.pixel_aspect_ratio,
+            ));
+        }
+
+        if let Some(tube) = self.tube {
+            if tube.width == 0 || tube.height == 0 {
+                return Err(ElectronBeamError::InvalidTubeResolution {
+                    width: tube.width,
+                    height: tube.height,
+                });
+            }
+            if !(0.0..=1.0).contains(&tube.scanline_intensity) {
+                return Err(ElectronBeamError::InvalidScanlineIntensity(
+                    tube.scanline_intensity,
+                ));
+            }
+        }
+
+        if let Some(composite) = &self.composite {
+            if composite.chroma_blur > composite::MAX_CHROMA_BLUR {
+                return Err(ElectronBeamError::InvalidChromaBlur(composite.chroma_blur));
+            }
+            let fields = [
+                ("chroma bleed", composite.chroma_bleed),
+                ("dot crawl", composite.dot_crawl),
+                ("ringing", composite.ringing),
+            ];
+            for (field, value) in fields {
+                if !(value >= 0.0 && value.is_finite()) {
+                    return Err(ElectronBeamError::InvalidComposite { field, value });
+                }
+            }
+        }
+
+        if let BeamColor::Kelvin(kelvin) = self.beam_color {
+            if !(kelvin > 0.0 && kelvin.is_finite()) {
+                return Err(ElectronBeamError::InvalidBeamKelvin(kelvin));
+            }
+        }
+
+        if let Some(thickness) = self.beam_thickness {
+            if !(thickness > 0.0 && thickness <= 1.0) {
+                return Err(ElectronBeamError::InvalidBeamThickness(thickness));
+            }
+        }
+
+        self.highlight_tint.validate("highlight tint")?;
+        self.highlight_strength.validate("highlight strength")?;
+        for strength in self.highlight_strength.values() {
+            if !(strength >= 0.0 && strength.is_finite()) {
+                return Err(ElectronBeamError::InvalidHighlightStrength(strength));
+            }
+        }
+        self.chroma_split.validate("chroma split")?;
+        for split in self.chroma_split.values() {
+            if !(split >= 0.0 && split.is_finite()) {
+                return Err(ElectronBeamError::InvalidChromaSplit(split));
+            }
+        }
+        self.background.validate("background")?;
+
+        if let Some(timeline) = &self.timeline {
+            timeline.validate()?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Mutable view of a caller-owned RGBA8 frame with an arbitrary row stride
+struct FrameMut<'a> {
+    data: &'a mut [u8],
+    width: u32,
+    height: u32,
+    stride: usize,
+}
+
+impl<'a> FrameMut<'a> {
+    /// Wrap a buffer, checking it can hold `height` rows of `width` pixels
+    fn new(data: &'a mut [u8], width: u32, height: u32, stride: usize) -> Result<Self> {
+        let row_len = width as usize * 4;
+        if stride < row_len {
+            return Err(ElectronBeamError::InvalidStride {
+                stride,
+                min: row_len,
+            });
+        }
+
+        let needed = if height == 0 {
+            0
+        } else {
+            stride * (height as usize - 1) + row_len
+        };
+        if data.len() < needed {
+            return Err(ElectronBeamError::BufferTooSmall {
+                needed,
+                actual: data.len(),
+            });
+        }
+
+        Ok(Self {
+            data: &mut data[..needed],
+            width,
+            height,
+            stride,
+        })
+    }
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+/// Buffers for the composite source and tube frames, reused across draws
+///
+/// Each draw running at the same time takes its own buffers. Clones of a
+/// beam start with an empty pool.
+#[derive(Default)]
+struct ScratchPool(Mutex<Vec<Vec<u8>>>);
+
+impl ScratchPool {
+    /// Take a buffer of `len` bytes, allocating one if none is free
+    fn take(&self, len: usize) -> Vec<u8> {
+        let mut buffer = self.lock().pop().unwrap_or_default();
+        buffer.resize(len, 0);
+        buffer
+    }
+
+    /// Return a buffer for later draws
+    fn put(&self, buffer: Vec<u8>) {
+        self.lock().push(buffer);
+    }
+
+    /// Free every buffer not in use
+    fn clear(&self) {
+        *self.lock() = Vec::new();
+    }
+
+    /// Number of free buffers
+    fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Clone for ScratchPool {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+/// Source of the scale down effect, shrunk to the size of the current frame
+enum Scaled<'a> {
+    Exact(RgbaImage),
+    Pyramid(Cow<'a, MipPyramid>),
+}
+
+/// Axis-aligned rectangle in pixel coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Quad {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+/// Animated parameters of the configuration, evaluated for one frame
+#[derive(Debug, Clone, Copy)]
+struct FrameParams {
+    highlight_tint: [u8; 3],
+    highlight_strength: f32,
+    chroma_split: f32,
+    background: [u8; 3],
+}
+
 /// The main ElectronBeam struct that handles CRT-style animations
+///
+/// Cloning is cheap: the prepared source and render pool are shared.
+#[derive(Clone)]
 pub struct ElectronBeam {
     config: ElectronBeamConfig,
-    source_image: Option<RgbaImage>,
+    timeline: Arc<Timeline>,
+    source_image: Option<Arc<RgbaImage>>,
+    /// The source in the phosphor colour, unless the composite pass changes
+    /// the source on every frame
+    ///
+    /// Built in `prepare`, and again on first use after `dismiss_resources`,
+    /// like the pyramid the scale down effect samples.
+    phosphor_source: OnceLock<Arc<RgbaImage>>,
+    pyramid: OnceLock<Arc<MipPyramid>>,
     prepared: bool,
+    pool: Option<Arc<rayon::ThreadPool>>,
+    scratch: ScratchPool,
 }
 
 impl ElectronBeam {
-    /// Create a new ElectronBeam instance
-    pub fn new(config: ElectronBeamConfig) -> Self {
-        Self {
+    /// Create a new ElectronBeam instance, validating the configuration
+    pub fn new(config: ElectronBeamConfig) -> Result<Self> {
+        config.validate()?;
+
+        let pool = if config.threads > 1 {
+            match rayon::ThreadPoolBuilder::new()
+                .num_threads(config.threads)
+                .build()
+            {
+                Ok(pool) => Some(Arc::new(pool)),
+                Err(err) => {
+                    log::warn!("Failed to create render thread pool, using the global one: {err}");
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let timeline = match &config.timeline {
+            Some(timeline) => timeline.clone(),
+            None => Timeline::for_config(&config),
+        };
+
+        Ok(Self {
+            timeline: Arc::new(timeline),
             config,
             source_image: None,
+            phosphor_source: OnceLock::new(),
+            pyramid: OnceLock::new(),
             prepared: false,
-        }
+            pool,
+            scratch: ScratchPool::default(),
+        })
+    }
+
+    /// Prepare the animation and return it as a [`PreparedBeam`]
+    ///
+    /// Unlike [`prepare`](Self::prepare), the result can only exist once a
+    /// source has been set, so it never fails to draw for lack of one.
+    pub fn into_prepared(mut self, image: RgbaImage) -> Result<PreparedBeam> {
+        self.prepare(image)?;
+        Ok(PreparedBeam::new(self))
     }
 
     /// Prepare the animation with a source image
     pub fn prepare(&mut self, image: RgbaImage) -> Result<()> {
-        // Resize image to match config dimensions if needed
-        let resized_image =
-            if image.width() != self.config.width || image.height() != self.config.height {
-                image::imageops::resize(
-                    &image,
-                    self.config.width,
-                    self.config.height,
-                    image::imageops::FilterType::Lanczos3,
-                )
-            } else {
-                image
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ElectronBeamError::EmptySourceImage {
+                width: image.width(),
+                height: image.height(),
+            });
+        }
+
+        // Resize image to match the render dimensions if needed
+        let (width, height) = self.render_size();
+        let resized_image = if image.width() != width || image.height() != height {
+            image::imageops::resize(&image, width, height, image::imageops::FilterType::Lanczos3)
+        } else {
+            image
+        };
+
+        self.phosphor_source = OnceLock::new();
+        self.pyramid = OnceLock::new();
+
+        // The phosphor tint and the pyramid the scale down effect samples can be
+        // built ahead of time unless the composite pass changes the source per frame
+        if self.config.composite.is_none() {
+            let source = match self.config.phosphor {
+                Some(phosphor) => self.phosphor_source(&resized_image, phosphor),
+                None => &resized_image,
             };
+            if self.timeline.contains(&Phase::Scale)
+                && self.config.scale_quality == ScaleQuality::Trilinear
+            {
+                self.pyramid(source);
+            }
+        }
 
-        self.source_image = Some(resized_image);
+        self.source_image = Some(Arc::new(resized_image));
         self.prepared = true;
         Ok(())
     }
 
+    /// The source in the phosphor colour, tinted on first use
+    fn phosphor_source(&self, source: &RgbaImage, phosphor: Phosphor) -> &RgbaImage {
+        self.phosphor_source.get_or_init(|| {
+            let mut tinted = source.clone();
+            phosphor.tint(&mut tinted);
+            Arc::new(tinted)
+        })
+    }
+
+    /// The pyramid of the source the scale down effect samples, built on first use
+    fn pyramid(&self, source: &RgbaImage) -> &MipPyramid {
+        self.pyramid
+            .get_or_init(|| Arc::new(MipPyramid::build(source)))
+    }
+
     /// Generate a frame at the specified animation level (0.0 to 1.0)
     pub fn draw(&self, level: f32) -> Result<RgbaImage> {
-        if !self.prepared {
-            return Err(ElectronBeamError::NotPrepared.into());
+        let mut output = ImageBuffer::new(self.config.width, self.config.height);
+        self.draw_into(level, &mut output)?;
+        Ok(output)
+    }
+
+    /// Draw a frame at the specified animation level into a caller-owned image
+    ///
+    /// The image must have the configured output dimensions. Reusing the same
+    /// image across frames avoids allocating a new frame buffer on every call.
+    pub fn draw_into(&self, level: f32, output: &mut RgbaImage) -> Result<()> {
+        let (width, height) = output.dimensions();
+        if (width, height) != (self.config.width, self.config.height) {
+            return Err(ElectronBeamError::DimensionMismatch {
+                expected_width: self.config.width,
+                expected_height: self.config.height,
+                actual_width: width,
+                actual_height: height,
+            });
         }
 
-        if level < 0.0 || level > 1.0 {
-            return Err(ElectronBeamError::InvalidLevel(level).into());
+        self.draw_into_raw(level, output, width as usize * 4)
+    }
+
+    /// Draw a frame at the specified animation level into a raw RGBA8 buffer
+    ///
+    /// Rows of the configured output width start every `stride` bytes, so the
+    /// buffer can be a mapped texture or a sub-rectangle of a larger surface.
+    /// Bytes between the end of a row and the next stride are left untouched.
+    pub fn draw_into_raw(&self, level: f32, data: &mut [u8], stride: usize) -> Result<()> {
+        let source = match &self.source_image {
+            Some(source) if self.prepared => source.as_ref(),
+            _ => return Err(ElectronBeamError::NotPrepared),
+        };
+
+        if !(0.0..=1.0).contains(&level) {
+            return Err(ElectronBeamError::InvalidLevel(level));
         }
 
-        let source = self.source_image.as_ref().unwrap();
-        let mut output = ImageBuffer::new(self.config.width, self.config.height);
+        let mut output = FrameMut::new(data, self.config.width, self.config.height, stride)?;
 
-        match self.config.mode {
-            AnimationMode::Fade => self.draw_fade(source, &mut output, level),
-            AnimationMode::ScaleDown => self.draw_scale_down(source, &mut output, level),
-            AnimationMode::WarmUp | AnimationMode::CoolDown => {
-                if level < self.config.v_stretch_duration {
-                    self.draw_v_stretch(source, &mut output, level / self.config.v_stretch_duration)
-                } else {
-                    let h_level =
-                        (level - self.config.v_stretch_duration) / self.config.h_stretch_duration;
-                    self.draw_h_stretch(source, &mut output, h_level)
-                }
+        let mut composited = None;
+        let source = match (&self.config.composite, self.config.phosphor) {
+            (Some(composite), phosphor) => {
+                let (width, height) = source.dimensions();
+                let row_len = width as usize * 4;
+                let mut scratch = self.scratch.take(row_len * height as usize);
+                let mut frame = FrameMut::new(&mut scratch, width, height, row_len)?;
+                self.for_each_row(&mut frame, |y, row| {
+                    composite::apply_row(source, composite, level, y, row);
+                    if let Some(phosphor) = phosphor {
+                        phosphor.tint(row);
+                    }
+                });
+                let frame = RgbaImage::from_raw(width, height, scratch)
+                    .expect("scratch is sized to the source");
+                &*composited.insert(frame)
             }
+            (None, Some(phosphor)) => self.phosphor_source(source, phosphor),
+            (None, None) => source,
+        };
+
+        match self.config.tube {
+            Some(tube) => {
+                let row_len = tube.width as usize * 4;
+                let mut scratch = self.scratch.take(row_len * tube.height as usize);
+
+                let mut tube_frame = FrameMut::new(&mut scratch, tube.width, tube.height, row_len)?;
+                self.render(source, &mut tube_frame, level);
+                self.upscale_tube(&scratch, tube, &mut output);
+
+                self.scratch.put(scratch);
+            }
+            None => self.render(source, &mut output, level),
         }
 
-        Ok(output)
+        if let Some(frame) = composited {
+            self.scratch.put(frame.into_raw());
+        }
+        Ok(())
+    }
+
+    /// Lazily render `count` frames evenly spread over the animation
+    ///
+    /// Each frame is drawn when the iterator reaches it, so memory use does not
+    /// grow with the number of frames. Frames are [`DEFAULT_FRAME_DELAY`] apart
+    /// unless changed with [`Frames::delay`].
+    pub fn frames(&self, count: u32) -> Frames<'_> {
+        Frames::new(self, count, DEFAULT_FRAME_DELAY)
+    }
+
+    /// Lazily render the animation at `fps` frames per second over `duration`
+    pub fn frames_at(&self, fps: f32, duration: Duration) -> Frames<'_> {
+        let count = (fps * duration.as_secs_f32()).round().max(1.0) as u32;
+        Frames::new(self, count, duration / count)
+    }
+
+    /// Render the effect at the specified level into a frame of the render size
+    fn render(&self, source: &RgbaImage, output: &mut FrameMut, level: f32) {
+        let (segment, progress) = self.timeline.locate(level);
+        let params = self.params_at(level);
+        match &segment.phase {
+            Phase::Hold => self.draw_hold(source, output),
+            Phase::VStretch => self.draw_v_stretch(source, output, progress, &params),
+            Phase::HStretch => self.draw_h_stretch(source, output, progress, &params),
+            Phase::Dot => self.draw_dot(output, progress, &params),
+            Phase::Fade => self.draw_fade(source, output, progress),
+            Phase::Scale => self.draw_scale_down(source, output, progress, &params),
+            Phase::Custom { render, .. } => {
+                // The output may be strided, so the phase draws into a cleared
+                // scratch frame that is then copied over
+                let (width, height) = output.dimensions();
+                let row_len = width as usize * 4;
+                let mut scratch = self.scratch.take(row_len * height as usize);
+                scratch.fill(0);
+                let mut frame = RgbaImage::from_raw(width, height, scratch)
+                    .expect("scratch is sized to the frame");
+                render(source, progress, &mut frame);
+
+                self.for_each_row(output, |y, row| {
+                    row.copy_from_slice(&frame.as_raw()[y as usize * row_len..][..row_len]);
+                });
+                self.scratch.put(frame.into_raw());
+            }
+        }
+    }
+
+    /// Size of the frames the effect is rendered at, before any tube upscale
+    fn render_size(&self) -> (u32, u32) {
+        match self.config.tube {
+            Some(tube) => (tube.width, tube.height),
+            None => (self.config.width, self.config.height),
+        }
+    }
+
+    /// Upscale a tube frame to the output by the largest integer factor that fits
+    ///
+    /// Every tube row becomes a block of output rows; the last row of each block
+    /// is the gap between scanlines and is darkened by the scanline intensity.
+    fn upscale_tube(&self, tube_frame: &[u8], tube: TubeResolution, output: &mut FrameMut) {
+        let (tube_width, tube_height) = (tube.width, tube.height);
+        let (factor, offset_x, offset_y) = self.tube_placement(tube);
+        let gap_scale = 1.0 - tube.scanline_intensity.clamp(0.0, 1.0);
+
+        self.for_each_row(output, |y, row| {
+            let tube_row = y
+                .checked_sub(offset_y)
+                .map(|dy| (dy / factor, dy % factor == factor - 1 && factor > 1))
+                .filter(|&(ty, _)| ty < tube_height);
+
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let tx = (x as u32)
+                    .checked_sub(offset_x)
+                    .map(|dx| dx / factor)
+                    .filter(|&tx| tx < tube_width);
+                let (Some(tx), Some((ty, is_gap))) = (tx, tube_row) else {
+                    pixel.copy_from_slice(&[0, 0, 0, 255]);
+                    continue;
+                };
+
+                let src = ((ty * tube_width + tx) * 4) as usize;
+                pixel.copy_from_slice(&tube_frame[src..src + 4]);
+                if is_gap {
+                    for value in &mut pixel[..3] {
+                        *value = (*value as f32 * gap_scale) as u8;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Integer upscale factor of a tube frame and its offset in the output
+    fn tube_placement(&self, tube: TubeResolution) -> (u32, u32, u32) {
+        let (out_width, out_height) = (self.config.width, self.config.height);
+        let factor = (out_width / tube.width)
+            .min(out_height / tube.height)
+            .max(1);
+        let offset_x = out_width.saturating_sub(tube.width * factor) / 2;
+        let offset_y = out_height.saturating_sub(tube.height * factor) / 2;
+        (factor, offset_x, offset_y)
+    }
+
+    /// Draw the source unchanged
+    fn draw_hold(&self, source: &RgbaImage, output: &mut FrameMut) {
+        let row_len = source.width() as usize * 4;
+        self.for_each_row(output, |y, row| {
+            row.copy_from_slice(&source.as_raw()[y as usize * row_len..][..row_len]);
+        });
+    }
+
+    /// Draw the dot left in the centre after the beam line, shrinking and fading
+    ///
+    /// The dot starts three line thicknesses wide, in display units like the
+    /// line, and its edge is anti-aliased over one pixel.
+    fn draw_dot(&self, output: &mut FrameMut, progress: f32, params: &FrameParams) {
+        let (dw, dh) = self.display_size();
+        let radius = self.dot_radius(progress);
+        let color = self.beam_color().map(|c| c as f32 * (1.0 - progress));
+        let par = self.config.pixel_aspect_ratio;
+        let [r, g, b] = params.background;
+
+        self.for_each_row(output, |y, row| {
+            simd::fill(row, [r, g, b, 255]);
+
+            let dy = (y as f32 + 0.5) / par - dh * 0.5;
+            if dy.abs() > radius + 1.0 {
+                return;
+            }
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let dx = x as f32 + 0.5 - dw * 0.5;
+                let coverage = (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                for i in 0..3 {
+                    pixel[i] = (params.background[i] as f32 + color[i] * coverage).min(255.0) as u8;
+                }
+            }
+        });
     }
 
     /// Draw a simple fade effect
-    fn draw_fade(&self, source: &RgbaImage, output: &mut RgbaImage, level: f32) {
+    fn draw_fade(&self, source: &RgbaImage, output: &mut FrameMut, level: f32) {
         let alpha = if self.config.mode == AnimationMode::WarmUp {
             level
         } else {
             1.0 - level
         };
 
-        for (x, y, pixel) in source.enumerate_pixels() {
-            let mut new_pixel = *pixel;
-            new_pixel[3] = (new_pixel[3] as f32 * alpha) as u8;
-            output.put_pixel(x, y, new_pixel);
-        }
+        let row_len = source.width() as usize * 4;
+        self.for_each_row(output, |y, row| {
+            let src_row = &source.as_raw()[y as usize * row_len..][..row_len];
+            simd::scale_alpha(row, src_row, alpha);
+        });
     }
 
     /// Draw the scale down effect
-    fn draw_scale_down(&self, source: &RgbaImage, output: &mut RgbaImage, level: f32) {
-        // Clear to black
-        for pixel in output.pixels_mut() {
-            *pixel = Rgba([0, 0, 0, 255]);
-        }
-
-        let curved_scale = self.scurve(level, 8.0);
-        let scale = if self.config.mode == AnimationMode::WarmUp {
-            curved_scale
-        } else {
-            1.0 - curved_scale
-        };
-
-        let new_width = (self.config.width as f32 * scale) as u32;
-        let new_height = (self.config.height as f32 * scale) as u32;
+    ///
+    /// The scale is uniform on both axes, so the displayed aspect ratio is kept
+    /// whatever the pixel aspect ratio.
+    fn draw_scale_down(
+        &self,
+        source: &RgbaImage,
+        output: &mut FrameMut,
+        level: f32,
+        params: &FrameParams,
+    ) {
+        let (curved_scale, scale) = self.scale_down_curve(level);
+        let quad = self.scale_down_quad(scale);
+        let (new_width, new_height) = (quad.w as u32, quad.h as u32);
 
-        if new_width > 0 && new_height > 0 {
-            let scaled = image::imageops::resize(
+        let scaled = (new_width > 0 && new_height > 0).then(|| match self.config.scale_quality {
+            ScaleQuality::ExactLanczos => Scaled::Exact(image::imageops::resize(
                 source,
                 new_width,
                 new_height,
                 image::imageops::FilterType::Lanczos3,
-            );
+            )),
+            // The composite pass changes the source on every frame
+            ScaleQuality::Trilinear => Scaled::Pyramid(match self.config.composite {
+                Some(_) => Cow::Owned(MipPyramid::build(source)),
+                None => Cow::Borrowed(self.pyramid(source)),
+            }),
+        });
+
+        let (offset_x, offset_y) = (quad.x as u32, quad.y as u32);
 
-            let offset_x = (self.config.width - new_width) / 2;
-            let offset_y = (self.config.height - new_height) / 2;
+        // Apply dimming effect
+        let dim_factor = if self.config.mode == AnimationMode::WarmUp {
+            scale
+        } else {
+            scale * (1.0 - curved_scale * 0.5)
+        };
+
+        // Minification of the source, as a pyramid level
+        let (source_width, source_height) = source.dimensions();
+        let step_x = source_width as f32 / new_width as f32;
+        let step_y = source_height as f32 / new_height as f32;
+        let lod = step_x.max(step_y).log2();
+
+        let [r, g, b] = params.background;
+        self.for_each_row(output, |y, row| {
+            // Clear to the background
+            simd::fill(row, [r, g, b, 255]);
 
             // Copy scaled image to center of output
-            for (x, y, pixel) in scaled.enumerate_pixels() {
-                let dest_x = x + offset_x;
-                let dest_y = y + offset_y;
-                if dest_x < self.config.width && dest_y < self.config.height {
-                    // Apply dimming effect
-                    let mut dimmed_pixel = *pixel;
-                    let dim_factor = if self.config.mode == AnimationMode::WarmUp {
-                        scale
+            let Some(scaled) = &scaled else {
+                return;
+            };
+            let Some(src_y) = y.checked_sub(offset_y).filter(|&y| y < new_height) else {
+                return;
+            };
+
+            let dest_row = &mut row[offset_x as usize * 4..][..new_width as usize * 4];
+            match scaled {
+                Scaled::Exact(scaled) => {
+                    let src_row = &scaled.as_raw()[src_y as usize * new_width as usize * 4..]
+                        [..new_width as usize * 4];
+                    simd::scale_rgb(dest_row, src_row, dim_factor);
+                }
+                Scaled::Pyramid(pyramid) => {
+                    let v = (src_y as f32 + 0.5) * step_y;
+                    for (x, dest) in dest_row.chunks_exact_mut(4).enumerate() {
+                        let u = (x as f32 + 0.5) * step_x;
+                        let src = pyramid.sample(source, u, v, lod).map(|c| c.round());
+                        for i in 0..3 {
+                            dest[i] = (src[i] * dim_factor) as u8;
+                        }
+                        dest[3] = src[3] as u8;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Draw the horizontal stretch effect (thin white line)
+    fn draw_h_stretch(
+        &self,
+        _source: &RgbaImage,
+        output: &mut FrameMut,
+        stretch: f32,
+        params: &FrameParams,
+    ) {
+        // Clear to the background, leaving nothing to draw once fully collapsed
+        let line = self.h_stretch_line(stretch).map(|(quad, [r, g, b])| {
+            let rows = quad.y as u32..(quad.y + quad.h) as u32;
+            let columns = quad.x as u32..(quad.x + quad.w) as u32;
+            (rows, columns, [r, g, b, 255])
+        });
+
+        let [r, g, b] = params.background;
+        self.for_each_row(output, |y, row| {
+            simd::fill(row, [r, g, b, 255]);
+
+            if let Some((rows, columns, line_color)) = &line {
+                if rows.contains(&y) {
+                    for x in columns.clone() {
+                        row[x as usize * 4..][..4].copy_from_slice(line_color);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Draw the vertical stretch effect with color separation
+    ///
+    /// All three channel planes and the highlight are composed in a single
+    /// pass: the source rows of each channel are worked out once per output
+    /// row, each row is finished while it is hot in cache, and rows outside all
+    /// of the collapsed quads are filled without sampling the source.
+    fn draw_v_stretch(
+        &self,
+        source: &RgbaImage,
+        output: &mut FrameMut,
+        stretch: f32,
+        params: &FrameParams,
+    ) {
+        let (frame_width, frame_height) = output.dimensions();
+
+        let [ar, ag, ab] = self.v_stretch_curves(stretch, params);
+        let quads = [ar, ag, ab].map(|a| self.v_stretch_quad(a));
+
+        // Add highlight for cool down mode, over the whole frame
+        let highlight = if self.config.mode == AnimationMode::CoolDown {
+            self.highlight_color(params, ag)
+        } else {
+            [0, 0, 0]
+        };
+        let [r, g, b] = params.background;
+        let cleared = [r, g, b, 0];
+        let background = [
+            r.saturating_add(highlight[0]),
+            g.saturating_add(highlight[1]),
+            b.saturating_add(highlight[2]),
+            0,
+        ];
+
+        let src_raw = source.as_raw();
+        self.for_each_row(output, |y, row| {
+            // Map the output row back to a source row for each channel
+            let src_rows = quads.map(|quad| {
+                let inside = (y as f32) >= quad.y && (y as f32) < (quad.y + quad.h);
+                inside.then(|| {
+                    let src_y = if quad.h > 0.0 {
+                        ((y as f32 - quad.y) / quad.h * frame_height as f32)
+                            .max(0.0)
+                            .min(frame_height as f32 - 1.0)
                     } else {
-                        scale * (1.0 - curved_scale * 0.5)
+                        frame_height as f32 * 0.5
                     };
+                    src_y as usize * frame_width as usize
+                })
+            });
 
-                    for i in 0..3 {
-                        dimmed_pixel[i] = (dimmed_pixel[i] as f32 * dim_factor) as u8;
+            if src_rows.iter().all(Option::is_none) {
+                simd::fill(row, background);
+                return;
+            }
+
+            simd::fill(row, cleared);
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                for channel in 0..3 {
+                    let (Some(src_row), quad) = (src_rows[channel], quads[channel]) else {
+                        continue;
+                    };
+                    if (x as f32) < quad.x || (x as f32) >= (quad.x + quad.w) {
+                        continue;
                     }
 
-                    output.put_pixel(dest_x, dest_y, dimmed_pixel);
+                    let src_x = if quad.w > 0.0 {
+                        ((x as f32 - quad.x) / quad.w * frame_width as f32)
+                            .max(0.0)
+                            .min(frame_width as f32 - 1.0)
+                    } else {
+                        frame_width as f32 * 0.5
+                    };
+
+                    pixel[channel] = src_raw[(src_row + src_x as usize) * 4 + channel];
+                    pixel[3] = 255; // Full alpha
                 }
             }
+
+            // Additive blending of the highlight over the channel planes
+            simd::add_rgb_saturating(row, highlight);
+        });
+    }
+
+    /// Compute the interpolation factors of the red, green and blue channels
+    ///
+    /// A monochrome phosphor has no separate guns, so nothing separates.
+    fn v_stretch_curves(&self, stretch: f32, params: &FrameParams) -> [f32; 3] {
+        let ag = self.scurve(stretch, 8.0);
+        if self.config.phosphor.is_some() {
+            return [ag; 3];
         }
+        let split = 0.5 * params.chroma_split;
+        [
+            self.scurve(stretch, 8.0 - split),
+            ag,
+            self.scurve(stretch, 8.0 + split),
+        ]
     }
 
-    /// Draw the horizontal stretch effect (thin white line)
-    fn draw_h_stretch(&self, _source: &RgbaImage, output: &mut RgbaImage, stretch: f32) {
-        // Clear to black
-        for pixel in output.pixels_mut() {
-            *pixel = Rgba([0, 0, 0, 255]);
-        }
-
-        if stretch < 1.0 {
-            let ag = self.scurve(stretch, 8.0);
-            let width = 2.0 * self.config.width as f32 * (1.0 - ag);
-            let height = 1.0f32.max(2.0);
-            let x_start = ((self.config.width as f32 - width) * 0.5) as u32;
-            let y_center = self.config.height / 2;
-            let half_height = (height * 0.5) as u32;
-
-            // Draw the horizontal line
-            let intensity = 1.0 - ag * 0.75;
-            let color_value = (255.0 * intensity) as u8;
-            let line_color = Rgba([color_value, color_value, color_value, 255]);
-
-            for x in x_start..((x_start as f32 + width) as u32).min(self.config.width) {
-                for y in y_center.saturating_sub(half_height)
-                    ..=(y_center + half_height).min(self.config.height - 1)
-                {
-                    output.put_pixel(x, y, line_color);
-                }
-            }
+    /// Compute the quad of a vertically collapsing channel, in storage pixels
+    ///
+    /// Mirrors Java's `setVStretchQuad`, laid out in display space so that the
+    /// overscan and collapse keep their proportions on non-square pixels.
+    fn v_stretch_quad(&self, a: f32) -> Quad {
+        let (dw, dh) = self.display_size();
+        let w = dw + (dw * a);
+        let h = dh - (dh * a);
+        self.display_to_storage(Quad {
+            x: (dw - w) * 0.5,
+            y: (dh - h) * 0.5,
+            w,
+            h,
+        })
+    }
+
+    /// Compute the quad of the collapsing beam line, in storage pixels
+    ///
+    /// Mirrors Java's `setHStretchQuad`. The line is two display units thick, so
+    /// it keeps the same visual weight whatever the pixel aspect ratio, unless a
+    /// thickness relative to the frame height is configured.
+    fn h_stretch_quad(&self, a: f32) -> Quad {
+        let (dw, dh) = self.display_size();
+        let w = 2.0 * dw * (1.0 - a);
+        let h = self
+            .config
+            .beam_thickness
+            .map_or(2.0, |thickness| thickness * dh);
+        self.display_to_storage(Quad {
+            x: (dw - w) * 0.5,
+            y: (dh - h) * 0.5,
+            w,
+            h,
+        })
+    }
+
+    /// Radius of the dot in display units: three line thicknesses wide at first
+    fn dot_radius(&self, progress: f32) -> f32 {
+        let thickness = self.h_stretch_quad(0.0).h / self.config.pixel_aspect_ratio;
+        1.5 * thickness * (1.0 - progress)
+    }
+
+    /// Curved level of the scale down effect and the resulting picture scale
+    fn scale_down_curve(&self, level: f32) -> (f32, f32) {
+        let curved_scale = self.scurve(level, 8.0);
+        let scale = if self.config.mode == AnimationMode::WarmUp {
+            curved_scale
+        } else {
+            1.0 - curved_scale
+        };
+        (curved_scale, scale)
+    }
+
+    /// Compute the centred quad of the scaled picture, in whole storage pixels
+    fn scale_down_quad(&self, scale: f32) -> Quad {
+        let (frame_width, frame_height) = self.render_size();
+        let width = (frame_width as f32 * scale) as u32;
+        let height = (frame_height as f32 * scale) as u32;
+        Quad {
+            x: (frame_width.saturating_sub(width) / 2) as f32,
+            y: (frame_height.saturating_sub(height) / 2) as f32,
+            w: width as f32,
+            h: height as f32,
         }
     }
 
-    /// Draw the vertical stretch effect with color separation
-    fn draw_v_stretch(&self, source: &RgbaImage, output: &mut RgbaImage, stretch: f32) {
-        // Clear to black
-        for pixel in output.pixels_mut() {
-            *pixel = Rgba([0, 0, 0, 0]);
+    /// Whole pixels covered by the beam line and its colour, `None` once collapsed
+    ///
+    /// The line is centred on the middle row and rounded out to whole rows.
+    fn h_stretch_line(&self, stretch: f32) -> Option<(Quad, [u8; 3])> {
+        if stretch >= 1.0 {
+            return None;
         }
 
-        // Compute interpolation scale factors for each color channel
-        let ar = self.scurve(stretch, 7.5);
+        let (frame_width, frame_height) = self.render_size();
         let ag = self.scurve(stretch, 8.0);
-        let ab = self.scurve(stretch, 8.5);
+        let quad = self.h_stretch_quad(ag);
+        let x_start = quad.x as u32;
+        let x_end = ((x_start as f32 + quad.w) as u32).min(frame_width);
+        let y_center = frame_height / 2;
+        let half_height = (quad.h * 0.5).round() as u32;
+        let y_start = y_center.saturating_sub(half_height);
+        let y_end = (y_center + half_height).min(frame_height - 1) + 1;
 
-        // Draw each color channel separately with different stretch factors
-        self.draw_v_stretch_channel(source, output, ar, 0); // Red
-        self.draw_v_stretch_channel(source, output, ag, 1); // Green
-        self.draw_v_stretch_channel(source, output, ab, 2); // Blue
+        let intensity = 1.0 - ag * 0.75;
+        let color = self.beam_color().map(|c| (c as f32 * intensity) as u8);
+        let pixels = Quad {
+            x: x_start as f32,
+            y: y_start as f32,
+            w: x_end.saturating_sub(x_start) as f32,
+            h: (y_end - y_start) as f32,
+        };
+        Some((pixels, color))
+    }
 
-        // Add white highlight for cool down mode
-        if self.config.mode == AnimationMode::CoolDown {
-            let highlight_intensity = ag;
-            self.add_highlight(output, highlight_intensity);
+    /// Size of the frame in display units, where one unit is the width of a pixel
+    fn display_size(&self) -> (f32, f32) {
+        let (width, height) = self.render_size();
+        (width as f32, height as f32 / self.config.pixel_aspect_ratio)
+    }
+
+    /// Map a quad from display units back to storage pixels
+    fn display_to_storage(&self, quad: Quad) -> Quad {
+        let par = self.config.pixel_aspect_ratio;
+        Quad {
+            y: quad.y * par,
+            h: quad.h * par,
+            ..quad
         }
     }
 
-    /// Draw a single color channel with vertical stretch
-    fn draw_v_stretch_channel(
-        &self,
-        source: &RgbaImage,
-        output: &mut RgbaImage,
-        stretch_factor: f32,
-        channel: usize,
-    ) {
-        let width = self.config.width as f32 + (self.config.width as f32 * stretch_factor);
-        let height = self.config.height as f32 - (self.config.height as f32 * stretch_factor);
-        let x_offset = (self.config.width as f32 - width) * 0.5;
-        let y_offset = (self.config.height as f32 - height) * 0.5;
-
-        // Sample and stretch the source image
-        for y in 0..self.config.height {
-            for x in 0..self.config.width {
-                // Map output coordinates back to source coordinates
-                let src_x = if width > 0.0 {
-                    ((x as f32 - x_offset) / width * self.config.width as f32)
-                        .max(0.0)
-                        .min(self.config.width as f32 - 1.0)
-                } else {
-                    self.config.width as f32 * 0.5
-                };
+    /// Colour added by the highlight at the given intensity, in the highlight tint
+    fn highlight_color(&self, params: &FrameParams, intensity: f32) -> [u8; 3] {
+        let tint = self
+            .config
+            .phosphor
+            .map_or(params.highlight_tint, |phosphor| phosphor.color());
+        let strength = params.highlight_strength * intensity;
+        tint.map(|c| (c as f32 * strength).min(255.0) as u8)
+    }
 
-                let src_y = if height > 0.0 {
-                    ((y as f32 - y_offset) / height * self.config.height as f32)
-                        .max(0.0)
-                        .min(self.config.height as f32 - 1.0)
-                } else {
-                    self.config.height as f32 * 0.5
-                };
+    /// Evaluate the animated parameters at an animation level
+    fn params_at(&self, level: f32) -> FrameParams {
+        FrameParams {
+            highlight_tint: self.config.highlight_tint.at(level),
+            highlight_strength: self.config.highlight_strength.at(level),
+            chroma_split: self.config.chroma_split.at(level),
+            background: self.config.background.at(level),
+        }
+    }
 
-                // Check if we're within the stretched bounds
-                if (x as f32) >= x_offset
-                    && (x as f32) < (x_offset + width)
-                    && (y as f32) >= y_offset
-                    && (y as f32) < (y_offset + height)
-                {
-                    let src_pixel = source.get_pixel(src_x as u32, src_y as u32);
-                    let mut dest_pixel = *output.get_pixel(x, y);
-
-                    // Blend the channel (additive blending for the CRT effect)
-                    let channel_value = src_pixel[channel];
-                    dest_pixel[channel] =
-                        (dest_pixel[channel] as u16 + channel_value as u16).min(255) as u8;
-                    dest_pixel[3] = 255; // Full alpha
-
-                    output.put_pixel(x, y, dest_pixel);
-                }
+    /// Run `f` on every row of an image, spread over the render thread pool
+    ///
+    /// Rows are independent, so the result is identical to the serial loop
+    /// used with a single thread.
+    fn for_each_row<F>(&self, frame: &mut FrameMut, f: F)
+    where
+        F: Fn(u32, &mut [u8]) + Send + Sync,
+    {
+        let row_len = frame.width as usize * 4;
+        if self.config.threads == 1 {
+            for (y, row) in frame.data.chunks_mut(frame.stride).enumerate() {
+                f(y as u32, &mut row[..row_len]);
             }
+            return;
         }
-    }
 
-    /// Add white highlight effect
-    fn add_highlight(&self, output: &mut RgbaImage, intensity: f32) {
-        let highlight_value = (255.0 * intensity) as u8;
+        let rows = frame.data.par_chunks_mut(frame.stride).enumerate();
+        let run = move || rows.for_each(|(y, row)| f(y as u32, &mut row[..row_len]));
 
-        for pixel in output.pixels_mut() {
-            // Add white highlight while preserving existing colors
-            for i in 0..3 {
-                pixel[i] = (pixel[i] as u16 + highlight_value as u16).min(255) as u8;
-            }
+        match &self.pool {
+            Some(pool) => pool.install(run),
+            None => run(),
         }
     }
 
+    /// Colour of the beam line: the phosphor colour or the configured beam colour
+    fn beam_color(&self) -> [u8; 3] {
+        self.config
+            .phosphor
+            .map_or(self.config.beam_color.to_rgb(), |phosphor| phosphor.color())
+    }
+
     /// S-curve interpolation function
     /// Interpolates a value in the range 0..1 along a sigmoid curve
     fn scurve(&self, value: f32, s: f32) -> f32 {
@@ -341,11 +1343,14 @@ impl ElectronBeam {
     /// Reset the animation state
     pub fn reset(&mut self) {
         self.source_image = None;
+        self.phosphor_source = OnceLock::new();
+        self.pyramid = OnceLock::new();
         self.prepared = false;
     }
 }
 
 /// Builder pattern for ElectronBeam configuration
+#[derive(Debug, Clone)]
 pub struct ElectronBeamBuilder {
     config: ElectronBeamConfig,
 }
@@ -357,6 +1362,11 @@ impl ElectronBeamBuilder {
         }
     }
 
+    /// Start from an existing configuration, e.g. one loaded from a file
+    pub fn from_config(config: ElectronBeamConfig) -> Self {
+        Self { config }
+    }
+
     pub fn dimensions(mut self, width: u32, height: u32) -> Self {
         self.config.width = width;
         self.config.height = height;
@@ -374,9 +1384,81 @@ impl ElectronBeamBuilder {
         self
     }
 
-    pub fn build(self) -> ElectronBeam {
+    pub fn pixel_aspect_ratio(mut self, ratio: f32) -> Self {
+        self.config.pixel_aspect_ratio = ratio;
+        self
+    }
+
+    pub fn tube(mut self, tube: TubeResolution) -> Self {
+        self.config.tube = Some(tube);
+        self
+    }
+
+    pub fn composite(mut self, composite: CompositeConfig) -> Self {
+        self.config.composite = Some(composite);
+        self
+    }
+
+    pub fn phosphor(mut self, phosphor: Phosphor) -> Self {
+        self.config.phosphor = Some(phosphor);
+        self
+    }
+
+    pub fn beam_color(mut self, color: BeamColor) -> Self {
+        self.config.beam_color = color;
+        self
+    }
+
+    pub fn beam_thickness(mut self, thickness: f32) -> Self {
+        self.config.beam_thickness = Some(thickness);
+        self
+    }
+
+    pub fn highlight(
+        mut self,
+        tint: impl Into<Animated<[u8; 3]>>,
+        strength: impl Into<Animated<f32>>,
+    ) -> Self {
+        self.config.highlight_tint = tint.into();
+        self.config.highlight_strength = strength.into();
+        self
+    }
+
+    pub fn chroma_split(mut self, split: impl Into<Animated<f32>>) -> Self {
+        self.config.chroma_split = split.into();
+        self
+    }
+
+    pub fn background(mut self, color: impl Into<Animated<[u8; 3]>>) -> Self {
+        self.config.background = color.into();
+        self
+    }
+
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.config.threads = threads;
+        self
+    }
+
+    pub fn scale_quality(mut self, quality: ScaleQuality) -> Self {
+        self.config.scale_quality = quality;
+        self
+    }
+
+    /// Replace the phases of the mode with a custom timeline
+    pub fn timeline(mut self, timeline: Timeline) -> Self {
+        self.config.timeline = Some(timeline);
+        self
+    }
+
+    /// Build the animation, failing if any setting is out of range
+    pub fn build(self) -> Result<ElectronBeam> {
         ElectronBeam::new(self.config)
     }
+
+    /// Build the animation and prepare it with a source image in one step
+    pub fn prepare(self, image: RgbaImage) -> Result<PreparedBeam> {
+        self.build()?.into_prepared(image)
+    }
 }
 
 impl Default for ElectronBeamBuilder {
@@ -388,13 +1470,15 @@ impl Default for ElectronBeamBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use image::Rgba;
 
     #[test]
     fn test_electron_beam_creation() {
         let beam = ElectronBeamBuilder::new()
             .dimensions(320, 240)
             .mode(AnimationMode::CoolDown)
-            .build();
+            .build()
+            .unwrap();
 
         assert_eq!(beam.config().width, 320);
         assert_eq!(beam.config().height, 240);
@@ -404,7 +1488,7 @@ mod tests {
 
     #[test]
     fn test_scurve_function() {
-        let beam = ElectronBeamBuilder::new().build();
+        let beam = ElectronBeamBuilder::new().build().unwrap();
 
         // Test boundary conditions
         assert!((beam.scurve(0.0, 8.0) - 0.0).abs() < 0.01);
@@ -412,9 +1496,126 @@ mod tests {
         assert!((beam.scurve(1.0, 8.0) - 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_build_validates_config() {
+        assert!(ElectronBeamBuilder::new().build().is_ok());
+        assert!(ElectronBeamBuilder::new()
+            .stretch_durations(0.0, 1.0)
+            .build()
+            .is_ok());
+
+        let error = |builder: ElectronBeamBuilder| builder.build().err().unwrap();
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().dimensions(0, 480)),
+            ElectronBeamError::InvalidDimensions {
+                width: 0,
+                height: 480
+            }
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().stretch_durations(1.0, 0.0)),
+            ElectronBeamError::InvalidVStretchDuration(_)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().stretch_durations(0.5, 0.0)),
+            ElectronBeamError::InvalidHStretchDuration(_)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().stretch_durations(0.5, 0.6)),
+            ElectronBeamError::StretchDurationSum { .. }
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().pixel_aspect_ratio(0.0)),
+            ElectronBeamError::InvalidPixelAspectRatio(_)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().tube(TubeResolution::new(0, 240))),
+            ElectronBeamError::InvalidTubeResolution { .. }
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().composite(CompositeConfig {
+                ringing: -1.0,
+                ..Default::default()
+            })),
+            ElectronBeamError::InvalidComposite {
+                field: "ringing",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().composite(CompositeConfig {
+                dot_crawl: f32::NAN,
+                ..Default::default()
+            })),
+            ElectronBeamError::InvalidComposite {
+                field: "dot crawl",
+                ..
+            }
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().composite(CompositeConfig {
+                chroma_blur: u32::MAX,
+                ..Default::default()
+            })),
+            ElectronBeamError::InvalidChromaBlur(u32::MAX)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().beam_color(BeamColor::Kelvin(f32::NAN))),
+            ElectronBeamError::InvalidBeamKelvin(_)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().beam_thickness(1.5)),
+            ElectronBeamError::InvalidBeamThickness(_)
+        ));
+        assert!(matches!(
+            error(ElectronBeamBuilder::new().highlight([255, 255, 255], -1.0)),
+            ElectronBeamError::InvalidHighlightStrength(_)
+        ));
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        assert_eq!(
+            "cool-down".parse::<AnimationMode>().unwrap(),
+            AnimationMode::CoolDown
+        );
+        assert_eq!(
+            "Scale_Down".parse::<AnimationMode>().unwrap(),
+            AnimationMode::ScaleDown
+        );
+        assert!(matches!(
+            "sideways".parse::<AnimationMode>(),
+            Err(ElectronBeamError::InvalidMode(name)) if name == "sideways"
+        ));
+
+        let mut beam = ElectronBeamBuilder::new().build().unwrap();
+        assert!(matches!(
+            beam.draw(0.5),
+            Err(ElectronBeamError::NotPrepared)
+        ));
+        assert!(matches!(
+            beam.prepare(RgbaImage::new(0, 10)),
+            Err(ElectronBeamError::EmptySourceImage {
+                width: 0,
+                height: 10
+            })
+        ));
+
+        // Frame errors keep the underlying cause
+        let error = beam.frames(3).next().unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "Failed to generate frame 1");
+        match error {
+            ElectronBeamError::Frame { frame, source } => {
+                assert_eq!(frame, 0);
+                assert!(matches!(*source, ElectronBeamError::NotPrepared));
+            }
+            error => panic!("unexpected error: {error}"),
+        }
+    }
+
     #[test]
     fn test_invalid_level() {
-        let beam = ElectronBeamBuilder::new().build();
+        let beam = ElectronBeamBuilder::new().build().unwrap();
 
         // These should return errors for invalid levels
         assert!(beam.draw(-0.1).is_err());
@@ -423,11 +1624,417 @@ mod tests {
 
     #[test]
     fn test_prepare_with_image() {
-        let mut beam = ElectronBeamBuilder::new().dimensions(100, 100).build();
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(100, 100)
+            .build()
+            .unwrap();
 
         let test_image = ImageBuffer::from_fn(50, 50, |_, _| Rgba([255, 255, 255, 255]));
 
         assert!(beam.prepare(test_image).is_ok());
         assert!(beam.is_prepared());
     }
+
+    #[test]
+    fn test_pixel_aspect_ratio_beam_thickness() {
+        let lit_rows = |ratio: f32| {
+            let mut beam = ElectronBeamBuilder::new()
+                .dimensions(40, 40)
+                .pixel_aspect_ratio(ratio)
+                .build()
+                .unwrap();
+            beam.prepare(ImageBuffer::from_pixel(40, 40, Rgba([255, 255, 255, 255])))
+                .unwrap();
+            let frame = beam.draw(0.6).unwrap();
+            (0..40).filter(|&y| frame.get_pixel(20, y)[0] > 0).count()
+        };
+
+        assert_eq!(lit_rows(1.0), 3);
+        // Wide pixels need more rows for the same displayed thickness
+        assert_eq!(lit_rows(2.0), 5);
+    }
+
+    #[test]
+    fn test_phosphor_has_no_color_fringe() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(32, 32)
+            .mode(AnimationMode::WarmUp)
+            .phosphor(Phosphor::Amber)
+            .build()
+            .unwrap();
+        let source =
+            ImageBuffer::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 60, 255]));
+        beam.prepare(source.clone()).unwrap();
+        // The source is tinted once, leaving the prepared source as given
+        assert_eq!(beam.source_image.as_deref(), Some(&source));
+        assert!(beam.phosphor_source.get().is_some());
+
+        let [pr, pg, pb] = Phosphor::Amber.color();
+        for level in [0.2, 0.4, 0.7] {
+            let frame = beam.draw(level).unwrap();
+            for pixel in frame.pixels() {
+                // Every lit pixel must be a shade of the phosphor colour
+                if pixel[0] > 0 {
+                    let scale = pixel[0] as f32 / pr as f32;
+                    assert!((pixel[1] as f32 - pg as f32 * scale).abs() <= 2.0);
+                    assert!((pixel[2] as f32 - pb as f32 * scale).abs() <= 2.0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_composite_frames_do_not_depend_on_earlier_draws() {
+        let source = ImageBuffer::from_fn(24, 16, |x, y| {
+            Rgba([(x * 10) as u8, (y * 15) as u8, 200, 255])
+        });
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(24, 16)
+            .tube(TubeResolution::new(12, 8))
+            .composite(CompositeConfig::default())
+            .phosphor(Phosphor::White)
+            .prepare(source)
+            .unwrap();
+
+        // The composite and tube scratch buffers are reused between draws
+        let levels = [0.0, 0.3, 0.6];
+        let forward = levels.map(|level| beam.draw(level).unwrap());
+        let backward: Vec<_> = levels
+            .iter()
+            .rev()
+            .map(|&l| beam.draw(l).unwrap())
+            .collect();
+        assert!(forward.iter().eq(backward.iter().rev()));
+        assert_ne!(forward[0], forward[1]);
+    }
+
+    #[test]
+    fn test_beam_color_temperature() {
+        assert_eq!(BeamColor::Rgb([1, 2, 3]).to_rgb(), [1, 2, 3]);
+
+        // Around 6500K is close to neutral white, warmer is orange, cooler is blue
+        let [r, g, b] = BeamColor::Kelvin(6504.0).to_rgb();
+        assert!(r >= 245 && g >= 245 && b >= 245);
+        let [r, _, b] = BeamColor::Kelvin(2700.0).to_rgb();
+        assert!(r == 255 && b < 200);
+        let [r, _, b] = BeamColor::Kelvin(12000.0).to_rgb();
+        assert!(b == 255 && r < 230);
+    }
+
+    #[test]
+    fn test_beam_line_and_highlight_settings() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(40, 40)
+            .beam_color(BeamColor::Rgb([255, 0, 0]))
+            .beam_thickness(0.25)
+            .highlight([0, 0, 255], 0.5)
+            .build()
+            .unwrap();
+        beam.prepare(ImageBuffer::from_pixel(40, 40, Rgba([0, 0, 0, 255])))
+            .unwrap();
+
+        let line = beam.draw(0.6).unwrap();
+        let lit: Vec<_> = (0..40).filter(|&y| line.get_pixel(20, y)[0] > 0).collect();
+        assert_eq!(lit.len(), 11);
+        assert_eq!(line.get_pixel(20, 20)[1], 0);
+
+        // The highlight only adds the blue tint, at half strength at most
+        let collapsing = beam.draw(0.45).unwrap();
+        let pixel = collapsing.get_pixel(20, 20);
+        assert_eq!((pixel[0], pixel[1]), (0, 0));
+        assert!(pixel[2] > 0 && pixel[2] <= 128);
+    }
+
+    #[test]
+    fn test_parallel_rendering_matches_serial() {
+        let source = ImageBuffer::from_fn(64, 48, |x, y| {
+            Rgba([(x * 4) as u8, (y * 5) as u8, ((x + y) * 2) as u8, 200])
+        });
+
+        let builders = [
+            AnimationMode::CoolDown,
+            AnimationMode::WarmUp,
+            AnimationMode::Fade,
+            AnimationMode::ScaleDown,
+        ]
+        .map(|mode| ElectronBeamBuilder::new().dimensions(64, 48).mode(mode));
+        let tube = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .tube(TubeResolution::new(32, 24))
+            .phosphor(Phosphor::Green);
+        let composite = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .composite(CompositeConfig::default())
+            .phosphor(Phosphor::Amber);
+
+        for builder in builders.into_iter().chain([tube, composite]) {
+            // One thread runs the plain serial loop, more run on a pool
+            let render = |threads| {
+                let beam = builder
+                    .clone()
+                    .threads(threads)
+                    .prepare(source.clone())
+                    .unwrap();
+                [0.0, 0.3, 0.6, 0.9].map(|level| beam.draw(level).unwrap().into_raw())
+            };
+
+            let serial = render(1);
+            assert_eq!(serial, render(4), "{:?}", builder);
+            assert_eq!(serial, render(0), "{:?}", builder);
+        }
+    }
+
+    /// The straightforward renderer the fused v-stretch replaced: one full
+    /// pass per channel plane followed by a highlight pass
+    fn reference_v_stretch(beam: &ElectronBeam, source: &RgbaImage, stretch: f32) -> RgbaImage {
+        let (width, height) = source.dimensions();
+        let mut output = ImageBuffer::from_pixel(width, height, Rgba([0, 0, 0, 0]));
+
+        let ag = beam.scurve(stretch, 8.0);
+        let factors = [beam.scurve(stretch, 7.5), ag, beam.scurve(stretch, 8.5)];
+        for (channel, a) in factors.into_iter().enumerate() {
+            let quad = beam.v_stretch_quad(a);
+            for y in 0..height {
+                for x in 0..width {
+                    let (xf, yf) = (x as f32, y as f32);
+                    if xf < quad.x || xf >= quad.x + quad.w || yf < quad.y || yf >= quad.y + quad.h
+                    {
+                        continue;
+                    }
+                    let src_x =
+                        ((xf - quad.x) / quad.w * width as f32).clamp(0.0, width as f32 - 1.0);
+                    let src_y =
+                        ((yf - quad.y) / quad.h * height as f32).clamp(0.0, height as f32 - 1.0);
+                    let value = source.get_pixel(src_x as u32, src_y as u32)[channel];
+                    let pixel = output.get_pixel_mut(x, y);
+                    pixel[channel] = (pixel[channel] as u16 + value as u16).min(255) as u8;
+                    pixel[3] = 255;
+                }
+            }
+        }
+
+        let highlight = beam.highlight_color(&beam.params_at(0.0), ag);
+        for pixel in output.pixels_mut() {
+            for i in 0..3 {
+                pixel[i] = (pixel[i] as u16 + highlight[i] as u16).min(255) as u8;
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn test_fused_v_stretch_matches_reference() {
+        let source = ImageBuffer::from_fn(48, 36, |x, y| {
+            Rgba([(x * 5) as u8, (y * 7) as u8, ((x * y) % 256) as u8, 255])
+        });
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(48, 36)
+            .build()
+            .unwrap();
+        beam.prepare(source.clone()).unwrap();
+
+        for stretch in [0.0, 0.1, 0.35, 0.5, 0.8, 0.99] {
+            let mut output = ImageBuffer::new(48, 36);
+            let mut frame = FrameMut::new(&mut output, 48, 36, 48 * 4).unwrap();
+            beam.draw_v_stretch(&source, &mut frame, stretch, &beam.params_at(0.0));
+            assert_eq!(
+                output,
+                reference_v_stretch(&beam, &source, stretch),
+                "{stretch}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_draw_into_reuses_buffers() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(16, 12)
+            .build()
+            .unwrap();
+        beam.prepare(ImageBuffer::from_fn(16, 12, |x, y| {
+            Rgba([(x * 16) as u8, (y * 20) as u8, 128, 255])
+        }))
+        .unwrap();
+
+        let mut frame = ImageBuffer::new(16, 12);
+        beam.draw_into(0.3, &mut frame).unwrap();
+        assert_eq!(frame, beam.draw(0.3).unwrap());
+        assert!(beam.draw_into(0.3, &mut ImageBuffer::new(8, 8)).is_err());
+
+        // Padding between rows of a strided buffer is left untouched
+        let stride = 16 * 4 + 8;
+        let mut raw = vec![7u8; stride * 12];
+        beam.draw_into_raw(0.3, &mut raw, stride).unwrap();
+        for (y, row) in raw.chunks(stride).enumerate() {
+            assert_eq!(&row[..64], &frame.as_raw()[y * 64..(y + 1) * 64]);
+            assert!(row[64..].iter().all(|&b| b == 7));
+        }
+
+        assert!(beam.draw_into_raw(0.3, &mut raw, 32).is_err());
+        assert!(beam.draw_into_raw(0.3, &mut raw[..100], stride).is_err());
+    }
+
+    #[test]
+    fn test_trilinear_scale_down_approximates_lanczos() {
+        let source = ImageBuffer::from_fn(96, 64, |x, y| {
+            Rgba([(x * 2) as u8, (y * 3) as u8, ((x + y) % 64 * 4) as u8, 255])
+        });
+        let render = |quality| {
+            let mut beam = ElectronBeamBuilder::new()
+                .dimensions(96, 64)
+                .mode(AnimationMode::ScaleDown)
+                .scale_quality(quality)
+                .build()
+                .unwrap();
+            beam.prepare(source.clone()).unwrap();
+            [0.2, 0.5, 0.7].map(|level| beam.draw(level).unwrap())
+        };
+
+        let exact = render(ScaleQuality::ExactLanczos);
+        let fast = render(ScaleQuality::Trilinear);
+        for (exact, fast) in exact.iter().zip(&fast) {
+            let total: u64 = exact
+                .as_raw()
+                .iter()
+                .zip(fast.as_raw())
+                .map(|(&a, &b)| (a as i64 - b as i64).unsigned_abs())
+                .sum();
+            let mean = total as f64 / exact.as_raw().len() as f64;
+            assert!(mean < 4.0, "mean difference {mean}");
+        }
+    }
+
+    #[test]
+    fn test_tube_resolution_upscale() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(20, 14)
+            .mode(AnimationMode::Fade)
+            .tube(TubeResolution {
+                width: 4,
+                height: 3,
+                scanline_intensity: 1.0,
+            })
+            .build()
+            .unwrap();
+        let source = ImageBuffer::from_fn(8, 6, |x, _| Rgba([(x * 30) as u8, 200, 100, 255]));
+        beam.prepare(source).unwrap();
+
+        let frame = beam.draw(0.0).unwrap();
+        assert_eq!(frame.dimensions(), (20, 14));
+
+        // Factor 4 leaves a 2 pixel border on the left and 1 pixel on top
+        assert_eq!(*frame.get_pixel(1, 5), Rgba([0, 0, 0, 255]));
+        assert_eq!(*frame.get_pixel(5, 0), Rgba([0, 0, 0, 255]));
+        // Each tube pixel covers a 4x4 block, the last row of which is the gap
+        assert_eq!(frame.get_pixel(2, 1), frame.get_pixel(5, 3));
+        assert_eq!(frame.get_pixel(2, 4)[1], 0);
+        assert_eq!(frame.get_pixel(2, 5)[1], 200);
+    }
+
+    #[test]
+    fn test_animated_parameters() {
+        let source =
+            ImageBuffer::from_fn(64, 48, |x, y| Rgba([(x * 4) as u8, (y * 5) as u8, 90, 255]));
+        let builder = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .highlight([255, 255, 255], 0.0);
+        let classic = builder.clone().prepare(source.clone()).unwrap();
+        let split_free = builder
+            .clone()
+            .chroma_split(0.0)
+            .prepare(source.clone())
+            .unwrap();
+        let beam = builder
+            .chroma_split(Animated::keyframes([(0.8, 0.0), (1.0, 1.0)]))
+            .background(Animated::keyframes([(0.5, [0, 0, 0]), (1.0, [0, 0, 200])]))
+            .prepare(source)
+            .unwrap();
+
+        // No colour separation until 80% of the animation
+        let early = beam.draw(0.15).unwrap();
+        assert_eq!(early, split_free.draw(0.15).unwrap());
+        assert_ne!(early, classic.draw(0.15).unwrap());
+
+        // The background fades in behind the beam line
+        let mut expected = classic.draw(1.0).unwrap();
+        expected.pixels_mut().for_each(|p| p[2] = 200);
+        assert_eq!(beam.draw(1.0).unwrap(), expected);
+
+        let error = ElectronBeamBuilder::new()
+            .highlight(
+                [255, 255, 255],
+                Animated::keyframes([(0.0, 1.0), (1.0, -1.0)]),
+            )
+            .build();
+        assert!(matches!(
+            error,
+            Err(ElectronBeamError::InvalidHighlightStrength(_))
+        ));
+    }
+
+    #[test]
+    fn test_timeline_dispatch() {
+        let source = ImageBuffer::from_fn(24, 16, |x, y| {
+            Rgba([(x * 10) as u8, (y * 15) as u8, 90, 255])
+        });
+        let builder = ElectronBeamBuilder::new()
+            .dimensions(24, 16)
+            .stretch_durations(0.4, 0.6);
+        let classic = builder.clone().prepare(source.clone()).unwrap();
+        let explicit = builder
+            .clone()
+            .timeline("vstretch:0.4,hstretch:0.6".parse().unwrap())
+            .prepare(source.clone())
+            .unwrap();
+        for level in [0.0, 0.2, 0.39, 0.4, 0.7, 1.0] {
+            assert_eq!(explicit.draw(level).unwrap(), classic.draw(level).unwrap());
+        }
+
+        let beam = builder
+            .clone()
+            .timeline(
+                "hold:0.2,vstretch:0.4,hstretch:0.2,dot:0.2"
+                    .parse()
+                    .unwrap(),
+            )
+            .prepare(source.clone())
+            .unwrap();
+        assert_eq!(beam.draw(0.1).unwrap(), source);
+        assert_eq!(beam.draw(0.4).unwrap(), classic.draw(0.2).unwrap());
+        let dot = beam.draw(0.85).unwrap();
+        assert!(dot.get_pixel(12, 8)[0] > 0);
+        assert_eq!(*dot.get_pixel(0, 8), Rgba([0, 0, 0, 255]));
+        assert_eq!(
+            *beam.draw(1.0).unwrap().get_pixel(12, 8),
+            Rgba([0, 0, 0, 255])
+        );
+
+        let custom = Phase::custom("invert", |source, progress, output| {
+            for (dest, src) in output.pixels_mut().zip(source.pixels()) {
+                *dest = Rgba([255 - src[0], 255 - src[1], (progress * 255.0) as u8, 255]);
+            }
+        });
+        let beam = builder
+            .timeline(Timeline::new().then(custom, 1.0))
+            .prepare(source)
+            .unwrap();
+        assert_eq!(
+            *beam.draw(1.0).unwrap().get_pixel(1, 1),
+            Rgba([245, 240, 255, 255])
+        );
+
+        // Every frame of a custom phase starts out cleared
+        let marker = Phase::custom("marker", |_, progress, output| {
+            if progress == 0.0 {
+                output.put_pixel(0, 0, Rgba([255; 4]));
+            }
+        });
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(4, 4)
+            .timeline(Timeline::new().then(marker, 1.0))
+            .prepare(RgbaImage::new(4, 4))
+            .unwrap();
+        assert_eq!(*beam.draw(0.0).unwrap().get_pixel(0, 0), Rgba([255; 4]));
+        assert_eq!(*beam.draw(0.5).unwrap().get_pixel(0, 0), Rgba([0; 4]));
+    }
 }
@@ -0,0 +1,196 @@
+//! Animator lifecycle
+//!
+//! Mirrors the stages of the Java `ScreenStateAnimator`: a host prepares the
+//! effect ahead of time, draws it, then releases what it holds in steps so
+//! long-running programs control exactly when buffers are freed.
+
+use std::io;
+use std::sync::OnceLock;
+
+use image::RgbaImage;
+
+use crate::{ElectronBeam, Result};
+
+/// An effect with explicit prepare, draw and teardown stages
+///
+/// The expected sequence is `prepare`, any number of `draw` calls, then
+/// `dismiss` once the animation is over, and `destroy` when the animator
+/// will not be used again. `dismiss_resources` may be called at any point
+/// to free memory that `draw` can rebuild on demand.
+pub trait ScreenStateAnimator {
+    /// Capture the source image and build everything needed to draw it
+    fn prepare(&mut self, image: RgbaImage) -> Result<()>;
+
+    /// Draw the effect at `level`, from 0.0 (full image) to 1.0
+    fn draw(&self, level: f32) -> Result<RgbaImage>;
+
+    /// Release buffers derived from the source, keeping the animator prepared
+    ///
+    /// Later draws still work but may be slower until the next `prepare`.
+    fn dismiss_resources(&mut self);
+
+    /// Release the source and all derived resources
+    ///
+    /// The animator must be prepared again before drawing.
+    fn dismiss(&mut self);
+
+    /// Release everything, including resources kept across preparations
+    ///
+    /// For an [`ElectronBeam`] this includes its render thread pool: frames
+    /// drawn after preparing it again render on rayon's global pool.
+    fn destroy(&mut self);
+
+    /// Whether `prepare` has succeeded since the last `dismiss`
+    fn is_prepared(&self) -> bool;
+
+    /// Write a human-readable description of the animator state
+    fn dump(&self, out: &mut dyn io::Write) -> io::Result<()>;
+}
+
+impl ScreenStateAnimator for ElectronBeam {
+    fn prepare(&mut self, image: RgbaImage) -> Result<()> {
+        ElectronBeam::prepare(self, image)
+    }
+
+    fn draw(&self, level: f32) -> Result<RgbaImage> {
+        ElectronBeam::draw(self, level)
+    }
+
+    fn dismiss_resources(&mut self) {
+        log::debug!("dismissResources");
+        self.phosphor_source = OnceLock::new();
+        self.pyramid = OnceLock::new();
+        self.scratch.clear();
+    }
+
+    fn dismiss(&mut self) {
+        log::debug!("dismiss");
+        if self.prepared {
+            self.dismiss_resources();
+            self.reset();
+        }
+    }
+
+    fn destroy(&mut self) {
+        log::debug!("destroy");
+        self.dismiss();
+        // Documented on the trait: later frames render on the global rayon pool
+        self.pool = None;
+    }
+
+    fn is_prepared(&self) -> bool {
+        ElectronBeam::is_prepared(self)
+    }
+
+    fn dump(&self, out: &mut dyn io::Write) -> io::Result<()> {
+        let config = &self.config;
+        let (render_width, render_height) = self.render_size();
+
+        writeln!(out)?;
+        writeln!(out, "Electron Beam State:")?;
+        writeln!(out, "  prepared={}", self.prepared)?;
+        writeln!(out, "  mode={:?}", config.mode)?;
+        writeln!(out, "  width={}", config.width)?;
+        writeln!(out, "  height={}", config.height)?;
+        writeln!(out, "  renderSize={}x{}", render_width, render_height)?;
+        match &self.source_image {
+            Some(source) => writeln!(out, "  source={}x{}", source.width(), source.height())?,
+            None => writeln!(out, "  source=none")?,
+        }
+        writeln!(
+            out,
+            "  mipLevels={}",
+            self.pyramid.get().map_or(0, |p| p.len())
+        )?;
+        writeln!(out, "  scratchBuffers={}", self.scratch.len())?;
+        match &self.pool {
+            Some(pool) => writeln!(out, "  threads={}", pool.current_num_threads())?,
+            None if config.threads == 1 => writeln!(out, "  threads=1")?,
+            None => writeln!(out, "  threads=global")?,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimationMode, ElectronBeamBuilder, Phosphor, TubeResolution};
+
+    fn dump_string(animator: &dyn ScreenStateAnimator) -> String {
+        let mut out = Vec::new();
+        animator.dump(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_lifecycle_stages() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(16, 16)
+            .mode(AnimationMode::ScaleDown)
+            .threads(2)
+            .build()
+            .unwrap();
+        let animator: &mut dyn ScreenStateAnimator = &mut beam;
+
+        animator.prepare(RgbaImage::new(16, 16)).unwrap();
+        let before = animator.draw(0.5).unwrap();
+        assert!(dump_string(animator).contains("mipLevels=5"));
+
+        // Derived buffers are rebuilt on demand, once, with identical output
+        animator.dismiss_resources();
+        assert!(animator.is_prepared());
+        assert!(dump_string(animator).contains("mipLevels=0"));
+        assert_eq!(animator.draw(0.5).unwrap(), before);
+        assert!(dump_string(animator).contains("mipLevels=5"));
+
+        animator.dismiss();
+        assert!(!animator.is_prepared());
+        assert!(animator.draw(0.5).is_err());
+
+        animator.destroy();
+        let state = dump_string(animator);
+        assert!(state.contains("prepared=false"));
+        assert!(state.contains("source=none"));
+        assert!(state.contains("threads=global"));
+    }
+
+    #[test]
+    fn test_dump_describes_state() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(32, 24)
+            .build()
+            .unwrap();
+        beam.prepare(RgbaImage::new(8, 8)).unwrap();
+
+        let state = dump_string(&beam);
+        assert!(state.starts_with("\nElectron Beam State:\n"));
+        assert!(state.contains("  prepared=true\n"));
+        assert!(state.contains("  mode=CoolDown\n"));
+        assert!(state.contains("  source=32x24\n"));
+    }
+
+    #[test]
+    fn test_dismiss_resources_frees_scratch_buffers() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(32, 24)
+            .tube(TubeResolution::new(16, 12))
+            .phosphor(Phosphor::Green)
+            .build()
+            .unwrap();
+        beam.prepare(RgbaImage::new(16, 12)).unwrap();
+        let before = beam.draw(0.3).unwrap();
+        assert!(dump_string(&beam).contains("  scratchBuffers=1\n"));
+
+        // Buffers of other beams are left alone
+        let other = beam.clone();
+        other.draw(0.3).unwrap();
+        beam.dismiss_resources();
+        assert!(dump_string(&beam).contains("  scratchBuffers=0\n"));
+        assert!(dump_string(&other).contains("  scratchBuffers=1\n"));
+        assert!(beam.phosphor_source.get().is_none());
+
+        assert_eq!(beam.draw(0.3).unwrap(), before);
+        assert!(beam.phosphor_source.get().is_some());
+    }
+}
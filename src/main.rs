@@ -5,13 +5,20 @@
 //! and vertical stretching and color separation.
 
 use anyhow::{Context, Result};
-use clap::{Parser, ValueEnum};
-use electron_beam::{AnimationMode, ElectronBeam, ElectronBeamBuilder};
+use clap::builder::PossibleValuesParser;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use electron_beam::{
+    AnimationFrame, AnimationMode, BeamColor, CompositeConfig, ElectronBeam, ElectronBeamBuilder,
+    ElectronBeamConfig, Phosphor, Quad, ScaleQuality, Timeline, TubeResolution, PRESETS,
+};
 use gif::{Encoder, Frame, Repeat};
 use image::RgbaImage;
 use log::{debug, info, warn};
+use std::collections::HashSet;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, ValueEnum)]
 enum CliAnimationMode {
@@ -36,18 +43,45 @@ impl From<CliAnimationMode> for AnimationMode {
     }
 }
 
+#[derive(Debug, Clone, ValueEnum)]
+enum CliPhosphor {
+    /// P1 green
+    Green,
+    /// P3 amber
+    Amber,
+    /// P4 white
+    White,
+}
+
+impl From<CliPhosphor> for Phosphor {
+    fn from(phosphor: CliPhosphor) -> Self {
+        match phosphor {
+            CliPhosphor::Green => Phosphor::Green,
+            CliPhosphor::Amber => Phosphor::Amber,
+            CliPhosphor::White => Phosphor::White,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Parser)]
 #[command(name = "electron-beam")]
 #[command(about = "Create CRT-style turn-off animations from PNG images")]
-#[command(version)]
+#[command(version, subcommand_negates_reqs = true)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Input PNG file path
-    #[arg(short, long)]
-    input: PathBuf,
+    #[arg(short, long, required = true)]
+    input: Option<PathBuf>,
 
-    /// Output GIF file path
-    #[arg(short, long)]
-    output: PathBuf,
+    /// Output GIF file path, or .css/.svg for a web animation
+    #[arg(short, long, required = true)]
+    output: Option<PathBuf>,
+
+    /// Start from a named preset (see `presets list`); other settings override it
+    #[arg(long, value_parser = preset_names())]
+    preset: Option<String>,
 
     /// Animation mode
     #[arg(short, long, default_value = "cool-down")]
@@ -77,6 +111,70 @@ struct Cli {
     #[arg(long, default_value = "0.5")]
     h_stretch: f32,
 
+    /// Phase sequence replacing the mode's, e.g. hold:0.1,vstretch:0.4,hstretch:0.4,dot:0.1
+    #[arg(long, value_name = "PHASE:DURATION[:EASING],...")]
+    timeline: Option<Timeline>,
+
+    /// Pixel aspect ratio of the output (e.g. 0.889 for 720x480 shown at 4:3)
+    #[arg(long, default_value = "1.0")]
+    pixel_aspect: f32,
+
+    /// Render at a low "tube" resolution (e.g. 320x240) and upscale to the output size
+    #[arg(long, value_parser = parse_resolution)]
+    tube: Option<(u32, u32)>,
+
+    /// Darkening of the gaps between tube scanlines (0.0 to 1.0)
+    #[arg(long, default_value = "0.0")]
+    scanlines: f32,
+
+    /// Simulate composite video artifacts (colour bleed, dot crawl, ringing)
+    #[arg(long)]
+    composite: bool,
+
+    /// Monochrome phosphor to emulate
+    #[arg(long)]
+    phosphor: Option<CliPhosphor>,
+
+    /// Beam line colour as RRGGBB hex
+    #[arg(long, value_parser = parse_color, conflicts_with = "beam_kelvin")]
+    beam_color: Option<[u8; 3]>,
+
+    /// Beam line colour as a colour temperature in Kelvin
+    #[arg(long)]
+    beam_kelvin: Option<f32>,
+
+    /// Beam line thickness as a fraction of the output height
+    #[arg(long)]
+    beam_thickness: Option<f32>,
+
+    /// Highlight tint as RRGGBB hex
+    #[arg(long, value_parser = parse_color, default_value = "ffffff")]
+    highlight_tint: [u8; 3],
+
+    /// Highlight strength (0.0 = none, 1.0 = classic)
+    #[arg(long, default_value = "1.0")]
+    highlight_strength: f32,
+
+    /// Separation of the colour channels during the vertical stretch (0.0 = none, 1.0 = classic)
+    #[arg(long, default_value = "1.0")]
+    chroma_split: f32,
+
+    /// Colour behind the collapsing picture, beam line and dot as RRGGBB hex
+    #[arg(long, value_parser = parse_color, default_value = "000000")]
+    background: [u8; 3],
+
+    /// Number of render threads per frame (0 = one per CPU core)
+    #[arg(long, default_value = "0")]
+    threads: usize,
+
+    /// Frames rendered concurrently ahead of the GIF encoder (0 = one per render thread, 1 = sequential)
+    #[arg(long, default_value = "0")]
+    jobs: usize,
+
+    /// Resize every scale-down frame with Lanczos3 instead of sampling a mip pyramid (slow)
+    #[arg(long)]
+    exact_scale: bool,
+
     /// Enable verbose logging
     #[arg(short, long)]
     verbose: bool,
@@ -92,10 +190,101 @@ struct Cli {
     /// Loop the animation
     #[arg(short, long)]
     loop_animation: bool,
+
+    /// URL of the source image in CSS or SVG output (defaults to the input path)
+    #[arg(long)]
+    image_url: Option<String>,
+
+    /// TOML or JSON file with animation settings; flags given on the command line take precedence
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Ids of the arguments given explicitly on the command line
+    #[arg(skip)]
+    explicit: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum Command {
+    /// Built-in presets
+    Presets {
+        #[command(subcommand)]
+        action: PresetsAction,
+    },
+    /// Print the phase, curves and quads of every frame instead of rendering
+    Inspect,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum PresetsAction {
+    /// List the presets with their descriptions
+    List,
+}
+
+impl Cli {
+    /// Input path, required by clap unless a subcommand is given
+    fn input(&self) -> &PathBuf {
+        self.input.as_ref().expect("--input is required")
+    }
+
+    /// Output path, required by clap unless a subcommand is given
+    fn output(&self) -> &PathBuf {
+        self.output.as_ref().expect("--output is required")
+    }
+
+    /// Parse a command line, remembering which arguments were given explicitly
+    fn parse_explicit<I, T>(itr: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<std::ffi::OsString> + Clone,
+    {
+        let matches = Cli::command().try_get_matches_from(itr)?;
+        let mut args = Cli::from_arg_matches(&matches)?;
+        args.explicit = matches
+            .ids()
+            .filter(|id| matches.value_source(id.as_str()) == Some(ValueSource::CommandLine))
+            .map(|id| id.to_string())
+            .collect();
+        Ok(args)
+    }
+
+    /// Whether the argument should override the settings of a preset or config file
+    ///
+    /// Without either every argument applies, defaults included.
+    fn overrides(&self, id: &str) -> bool {
+        #[cfg(feature = "serde")]
+        if self.config.is_some() {
+            return self.explicit.contains(id);
+        }
+        self.preset.is_none() || self.explicit.contains(id)
+    }
+}
+
+/// Contents of a `--config` file
+///
+/// The animation settings of [`ElectronBeamConfig`] plus the CLI's own output
+/// settings, all optional.
+#[cfg(feature = "serde")]
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    preset: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    frames: Option<u32>,
+    duration: Option<u16>,
+    reverse: Option<bool>,
+    loop_animation: Option<bool>,
+    /// Animation settings, merged over the preset before being parsed
+    ///
+    /// Held as JSON values, which unlike TOML can be null to clear a setting.
+    #[serde(flatten)]
+    beam: serde_json::Map<String, serde_json::Value>,
 }
 
 fn main() -> Result<()> {
-    let args = Cli::parse();
+    let mut args = Cli::parse_explicit(std::env::args_os()).unwrap_or_else(|err| err.exit());
 
     // Initialize logging
     let log_level = if args.debug {
@@ -111,55 +300,109 @@ fn main() -> Result<()> {
     info!("Starting ElectronBeam CLI");
     debug!("Arguments: {:?}", args);
 
+    if let Some(Command::Presets {
+        action: PresetsAction::List,
+    }) = &args.command
+    {
+        print!("{}", list_presets());
+        return Ok(());
+    }
+
+    // Merge the preset and config file, if any, under the command line
+    let base_config = load_base_config(&mut args)?;
+
+    if let Some(Command::Inspect) = &args.command {
+        if args.frames == 0 {
+            anyhow::bail!("Frame count must be greater than 0");
+        }
+        // The frame size the render would use, read from the image header
+        let input = args
+            .input
+            .as_ref()
+            .map(|path| {
+                image::image_dimensions(path)
+                    .with_context(|| format!("Failed to open image: {}", path.display()))
+            })
+            .transpose()?;
+        let dimensions = output_dimensions(&args, input)?;
+        let beam = beam_builder(&args, base_config, dimensions).build()?;
+        print!("{}", inspect_table(&beam, args.frames, args.reverse)?);
+        return Ok(());
+    }
+
     // Validate arguments
     validate_arguments(&args)?;
 
     // Load the input image
-    info!("Loading input image: {}", args.input.display());
-    let input_image = load_image(&args.input)?;
+    info!("Loading input image: {}", args.input().display());
+    let input_image = load_image(args.input())?;
 
     // Determine output dimensions
-    let (width, height) = match (args.width, args.height) {
-        (Some(w), Some(h)) => (w, h),
-        (Some(w), None) => {
-            let aspect_ratio = input_image.height() as f32 / input_image.width() as f32;
-            (w, (w as f32 * aspect_ratio) as u32)
-        }
-        (None, Some(h)) => {
-            let aspect_ratio = input_image.width() as f32 / input_image.height() as f32;
-            ((h as f32 * aspect_ratio) as u32, h)
-        }
-        (None, None) => (input_image.width(), input_image.height()),
-    };
+    let (width, height) = output_dimensions(&args, Some(input_image.dimensions()))?;
 
     info!("Output dimensions: {}x{}", width, height);
 
-    // Create the ElectronBeam
-    let mut beam = ElectronBeamBuilder::new()
-        .dimensions(width, height)
-        .mode(args.mode.into())
-        .stretch_durations(args.v_stretch, args.h_stretch)
-        .build();
+    if let Some(format) = web_format(args.output()) {
+        let beam = beam_builder(&args, base_config, (width, height)).build()?;
+        info!("Exporting web animation: {}", args.output().display());
+        std::fs::write(args.output(), web_export(&beam, &args, format)?)
+            .with_context(|| format!("Failed to write {}", args.output().display()))?;
+        info!("Animation complete! Saved to: {}", args.output().display());
+        return Ok(());
+    }
 
     // Prepare the animation
     info!("Preparing animation...");
-    beam.prepare(input_image)?;
-
-    // Generate frames
-    info!("Generating {} frames...", args.frames);
-    let frames = generate_frames(&beam, args.frames, args.reverse)?;
+    let beam = beam_builder(&args, base_config, (width, height)).prepare(input_image)?;
+
+    // Generate frames, encoding each one as soon as it is rendered
+    info!(
+        "Generating {} frames into GIF: {}",
+        args.frames,
+        args.output().display()
+    );
+    let mut frames = beam
+        .frames(args.frames)
+        .delay(Duration::from_millis(args.duration as u64));
+    if args.reverse {
+        frames = frames.reversed();
+    }
 
-    // Create GIF
-    info!("Creating GIF: {}", args.output.display());
-    create_gif(&frames, &args.output, args.duration, args.loop_animation)?;
+    if args.jobs == 1 {
+        create_gif(frames, (width, height), args.output(), args.loop_animation)?;
+    } else {
+        let mut writer = GifWriter::create(args.output(), (width, height), args.loop_animation)?;
+        frames.render_parallel(args.jobs, |frame| Ok(writer.write(&frame)?))?;
+        writer.finish()?;
+    }
 
-    info!("Animation complete! Saved to: {}", args.output.display());
+    info!("Animation complete! Saved to: {}", args.output().display());
     Ok(())
 }
 
+/// Output size from the arguments, filling in missing dimensions from the
+/// input image's aspect ratio
+///
+/// Without an input both `--width` and `--height` are needed.
+fn output_dimensions(args: &Cli, input: Option<(u32, u32)>) -> Result<(u32, u32)> {
+    Ok(match (args.width, args.height, input) {
+        (Some(w), Some(h), _) => (w, h),
+        (Some(w), None, Some((input_width, input_height))) => {
+            let aspect_ratio = input_height as f32 / input_width as f32;
+            (w, (w as f32 * aspect_ratio) as u32)
+        }
+        (None, Some(h), Some((input_width, input_height))) => {
+            let aspect_ratio = input_width as f32 / input_height as f32;
+            ((h as f32 * aspect_ratio) as u32, h)
+        }
+        (None, None, Some(size)) => size,
+        _ => anyhow::bail!("--width and --height are required without --input"),
+    })
+}
+
 fn validate_arguments(args: &Cli) -> Result<()> {
-    if !args.input.exists() {
-        anyhow::bail!("Input file does not exist: {}", args.input.display());
+    if !args.input().exists() {
+        anyhow::bail!("Input file does not exist: {}", args.input().display());
     }
 
     if args.frames == 0 {
@@ -170,15 +413,7 @@ fn validate_arguments(args: &Cli) -> Result<()> {
         anyhow::bail!("Frame duration must be greater than 0");
     }
 
-    if args.v_stretch < 0.0 || args.v_stretch > 1.0 {
-        anyhow::bail!("Vertical stretch duration must be between 0.0 and 1.0");
-    }
-
-    if args.h_stretch < 0.0 || args.h_stretch > 1.0 {
-        anyhow::bail!("Horizontal stretch duration must be between 0.0 and 1.0");
-    }
-
-    if let Some(parent) = args.output.parent() {
+    if let Some(parent) = args.output().parent() {
         if !parent.exists() {
             warn!(
                 "Output directory does not exist, creating: {}",
@@ -193,69 +428,359 @@ fn validate_arguments(args: &Cli) -> Result<()> {
     Ok(())
 }
 
-fn load_image(path: &PathBuf) -> Result<RgbaImage> {
-    let img =
-        image::open(path).with_context(|| format!("Failed to open image: {}", path.display()))?;
+/// Web animation formats, chosen by the output extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WebFormat {
+    Css,
+    Svg,
+}
 
-    Ok(img.to_rgba8())
+fn web_format(output: &Path) -> Option<WebFormat> {
+    let extension = output.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "css" => Some(WebFormat::Css),
+        "svg" => Some(WebFormat::Svg),
+        _ => None,
+    }
 }
 
-fn generate_frames(beam: &ElectronBeam, frame_count: u32, reverse: bool) -> Result<Vec<RgbaImage>> {
-    let mut frames = Vec::with_capacity(frame_count as usize);
+/// CSS or SVG animation lasting as long as the GIF would
+fn web_export(beam: &ElectronBeam, args: &Cli, format: WebFormat) -> Result<String> {
+    let href = match &args.image_url {
+        Some(url) => url.clone(),
+        None => args.input().to_string_lossy().into_owned(),
+    };
+    let mut animation = beam
+        .web_animation(href)
+        .duration(Duration::from_millis(
+            args.frames as u64 * args.duration as u64,
+        ))
+        .looping(args.loop_animation);
+    if args.reverse {
+        animation = animation.reversed();
+    }
+    Ok(match format {
+        WebFormat::Css => animation.css()?,
+        WebFormat::Svg => animation.svg()?,
+    })
+}
 
-    for i in 0..frame_count {
-        let level = if reverse {
-            1.0 - (i as f32 / (frame_count - 1) as f32)
-        } else {
-            i as f32 / (frame_count - 1) as f32
-        };
+fn preset_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(PRESETS.iter().map(|preset| preset.name))
+}
+
+/// Table of the built-in presets for `presets list`
+fn list_presets() -> String {
+    let width = PRESETS.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    PRESETS
+        .iter()
+        .map(|preset| format!("{:width$}  {}\n", preset.name, preset.description))
+        .collect()
+}
+
+/// Table of the phase state of every frame for `inspect`
+fn inspect_table(beam: &ElectronBeam, count: u32, reverse: bool) -> Result<String> {
+    let mut frames = beam.frames(count);
+    if reverse {
+        frames = frames.reversed();
+    }
+    // Values that round to zero are printed without a minus sign
+    let tidy = |value: f32, precision: f32| if value.abs() < precision { 0.0 } else { value };
+    let quad = |q: Quad| {
+        let (x, y) = (tidy(q.x, 0.05), tidy(q.y, 0.05));
+        format!("{:.1},{:.1} {:.1}x{:.1}", x, y, q.w, q.h)
+    };
 
-        debug!(
-            "Generating frame {}/{} (level: {:.3})",
-            i + 1,
-            frame_count,
-            level
+    let mut table = format!(
+        "{:>5}  {:>6}  {:<10} {:>8}  {:>6} {:>6} {:>6}  {:<24} {:<24} {}\n",
+        "frame",
+        "level",
+        "phase",
+        "progress",
+        "red",
+        "green",
+        "blue",
+        "red quad",
+        "green quad",
+        "blue quad"
+    );
+    for index in 0..count {
+        let state = beam.phase_at(frames.level_at(index))?;
+        let [r, g, b] = state.curves.map(|curve| tidy(curve, 0.0005));
+        table += &format!(
+            "{:>5}  {:>6.3}  {:<10} {:>8.3}  {:>6.3} {:>6.3} {:>6.3}  {:<24} {:<24} {}\n",
+            index,
+            state.level,
+            state.phase.name(),
+            state.progress,
+            r,
+            g,
+            b,
+            quad(state.quads[0]),
+            quad(state.quads[1]),
+            quad(state.quads[2]),
         );
+    }
+    Ok(table)
+}
+
+/// Load the `--config` file over the preset and fill in the output settings it provides
+///
+/// Returns the animation settings of the preset with the file merged over
+/// them, or the defaults without either.
+#[cfg(feature = "serde")]
+fn load_base_config(args: &mut Cli) -> Result<ElectronBeamConfig> {
+    let Some(path) = args.config.clone() else {
+        return preset_config(args.preset.as_deref());
+    };
+
+    info!("Loading configuration: {}", path.display());
+    let text = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let file: ConfigFile = if path.extension().is_some_and(|ext| ext == "json") {
+        serde_json::from_str(&text).map_err(anyhow::Error::from)
+    } else {
+        toml::from_str(&text).map_err(anyhow::Error::from)
+    }
+    .with_context(|| format!("Invalid config file: {}", path.display()))?;
+
+    if args.width.is_none() {
+        args.width = file.width;
+    }
+    if args.height.is_none() {
+        args.height = file.height;
+    }
+    if args.preset.is_none() {
+        args.preset = file.preset;
+    }
+    if let Some(frames) = file.frames.filter(|_| !args.overrides("frames")) {
+        args.frames = frames;
+    }
+    if let Some(duration) = file.duration.filter(|_| !args.overrides("duration")) {
+        args.duration = duration;
+    }
+    if !args.reverse {
+        args.reverse = file.reverse.unwrap_or(false);
+    }
+    if !args.loop_animation {
+        args.loop_animation = file.loop_animation.unwrap_or(false);
+    }
+
+    let mut settings = match serde_json::to_value(preset_config(args.preset.as_deref())?)? {
+        serde_json::Value::Object(settings) => settings,
+        _ => unreachable!("a config serializes to an object"),
+    };
+    merge_settings(&mut settings, file.beam);
+    serde_json::from_value(serde_json::Value::Object(settings))
+        .with_context(|| format!("Invalid config file: {}", path.display()))
+}
+
+#[cfg(not(feature = "serde"))]
+fn load_base_config(args: &mut Cli) -> Result<ElectronBeamConfig> {
+    preset_config(args.preset.as_deref())
+}
+
+/// Configuration of the named preset, or the defaults
+fn preset_config(name: Option<&str>) -> Result<ElectronBeamConfig> {
+    Ok(match name {
+        Some(name) => ElectronBeamConfig::preset(name)?,
+        None => ElectronBeamConfig::default(),
+    })
+}
+
+/// Recursively merge `overlay` into `base`, keeping base keys it does not set
+///
+/// A null in `overlay` replaces the base value, clearing an optional setting.
+#[cfg(feature = "serde")]
+fn merge_settings(
+    base: &mut serde_json::Map<String, serde_json::Value>,
+    overlay: serde_json::Map<String, serde_json::Value>,
+) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(serde_json::Value::Object(base)), serde_json::Value::Object(overlay)) => {
+                merge_settings(base, overlay)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Apply the command line over `base` and turn it into an animation builder
+///
+/// Settings are validated by the library when the builder is built.
+fn beam_builder(
+    args: &Cli,
+    base: ElectronBeamConfig,
+    (width, height): (u32, u32),
+) -> ElectronBeamBuilder {
+    let mut config = ElectronBeamConfig {
+        width,
+        height,
+        ..base
+    };
+
+    if args.overrides("mode") {
+        config.mode = args.mode.clone().into();
+    }
+    if args.overrides("v_stretch") {
+        config.v_stretch_duration = args.v_stretch;
+    }
+    if args.overrides("h_stretch") {
+        config.h_stretch_duration = args.h_stretch;
+    }
+    if args.overrides("pixel_aspect") {
+        config.pixel_aspect_ratio = args.pixel_aspect;
+    }
+
+    if let Some((tube_width, tube_height)) = args.tube {
+        config.tube = Some(TubeResolution {
+            width: tube_width,
+            height: tube_height,
+            ..config
+                .tube
+                .unwrap_or(TubeResolution::new(tube_width, tube_height))
+        });
+    }
+    if let Some(tube) = config.tube.as_mut().filter(|_| args.overrides("scanlines")) {
+        tube.scanline_intensity = args.scanlines;
+    }
+    if let Some(tube) = config.tube {
+        info!("Tube resolution: {}x{}", tube.width, tube.height);
+    }
+
+    if args.composite && config.composite.is_none() {
+        config.composite = Some(CompositeConfig::default());
+    }
 
-        let frame = beam
-            .draw(level)
-            .with_context(|| format!("Failed to generate frame {}", i + 1))?;
+    if let Some(phosphor) = args.phosphor.clone() {
+        config.phosphor = Some(phosphor.into());
+    }
 
-        frames.push(frame);
+    if let Some(color) = args.beam_color {
+        config.beam_color = BeamColor::Rgb(color);
     }
 
-    Ok(frames)
+    if let Some(kelvin) = args.beam_kelvin {
+        config.beam_color = BeamColor::Kelvin(kelvin);
+    }
+
+    if let Some(thickness) = args.beam_thickness {
+        config.beam_thickness = Some(thickness);
+    }
+
+    if args.overrides("highlight_tint") {
+        config.highlight_tint = args.highlight_tint.into();
+    }
+    if args.overrides("highlight_strength") {
+        config.highlight_strength = args.highlight_strength.into();
+    }
+    if args.overrides("chroma_split") {
+        config.chroma_split = args.chroma_split.into();
+    }
+    if args.overrides("background") {
+        config.background = args.background.into();
+    }
+    if args.overrides("threads") {
+        config.threads = args.threads;
+    }
+
+    if args.exact_scale {
+        config.scale_quality = ScaleQuality::ExactLanczos;
+    }
+
+    if let Some(timeline) = &args.timeline {
+        config.timeline = Some(timeline.clone());
+    }
+
+    ElectronBeamBuilder::from_config(config)
+}
+
+fn parse_resolution(value: &str) -> Result<(u32, u32)> {
+    let (width, height) = value
+        .split_once('x')
+        .with_context(|| format!("Expected WIDTHxHEIGHT, got '{}'", value))?;
+    Ok((width.trim().parse()?, height.trim().parse()?))
+}
+
+fn parse_color(value: &str) -> Result<[u8; 3]> {
+    let hex = value.trim_start_matches('#');
+    // Checked before slicing, which would panic inside a multi-byte character
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("Expected a RRGGBB hex colour, got '{}'", value);
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16);
+    Ok([channel(0)?, channel(2)?, channel(4)?])
+}
+
+fn load_image(path: &PathBuf) -> Result<RgbaImage> {
+    let img =
+        image::open(path).with_context(|| format!("Failed to open image: {}", path.display()))?;
+
+    Ok(img.to_rgba8())
 }
 
 fn create_gif(
-    frames: &[RgbaImage],
+    frames: impl Iterator<Item = electron_beam::Result<AnimationFrame>>,
+    dimensions: (u32, u32),
     output_path: &PathBuf,
-    frame_duration: u16,
     loop_animation: bool,
 ) -> Result<()> {
-    if frames.is_empty() {
-        anyhow::bail!("No frames to write");
+    let mut writer = GifWriter::create(output_path, dimensions, loop_animation)?;
+    for frame in frames {
+        writer.write(&frame?)?;
     }
+    writer.finish()
+}
 
-    let output_file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+/// GIF encoder fed one frame at a time, in sequence order
+struct GifWriter {
+    encoder: Encoder<File>,
+    width: u16,
+    height: u16,
+    /// Reused across frames to avoid allocating a conversion buffer per frame
+    rgb_data: Vec<u8>,
+    written: usize,
+}
 
-    let (width, height) = (frames[0].width() as u16, frames[0].height() as u16);
-    let mut encoder = Encoder::new(output_file, width, height, &[])?;
+impl GifWriter {
+    fn create(
+        output_path: &PathBuf,
+        (width, height): (u32, u32),
+        loop_animation: bool,
+    ) -> Result<Self> {
+        let output_file = File::create(output_path)
+            .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
+
+        let (width, height) = (width as u16, height as u16);
+        let mut encoder = Encoder::new(output_file, width, height, &[])?;
+
+        // Set repeat mode
+        if loop_animation {
+            encoder.set_repeat(Repeat::Infinite)?;
+        } else {
+            encoder.set_repeat(Repeat::Finite(0))?;
+        }
 
-    // Set repeat mode
-    if loop_animation {
-        encoder.set_repeat(Repeat::Infinite)?;
-    } else {
-        encoder.set_repeat(Repeat::Finite(0))?;
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            rgb_data: Vec::with_capacity((width as usize) * (height as usize) * 3),
+            written: 0,
+        })
     }
 
-    for (i, frame_image) in frames.iter().enumerate() {
-        debug!("Writing frame {}/{}", i + 1, frames.len());
+    fn write(&mut self, frame: &AnimationFrame) -> Result<()> {
+        let i = frame.index as usize;
+        debug!("Writing frame {} (level: {:.3})", i + 1, frame.level);
 
         // Convert RGBA to RGB (GIF doesn't support alpha)
-        let mut rgb_data = Vec::with_capacity((width as usize) * (height as usize) * 3);
-        for pixel in frame_image.pixels() {
+        self.rgb_data.clear();
+        for pixel in frame.image.pixels() {
             let [r, g, b, a] = pixel.0;
 
             // Blend with black background based on alpha
@@ -264,20 +789,28 @@ fn create_gif(
             let blended_g = (g as f32 * alpha_f) as u8;
             let blended_b = (b as f32 * alpha_f) as u8;
 
-            rgb_data.push(blended_r);
-            rgb_data.push(blended_g);
-            rgb_data.push(blended_b);
+            self.rgb_data.push(blended_r);
+            self.rgb_data.push(blended_g);
+            self.rgb_data.push(blended_b);
         }
 
-        let mut frame = Frame::from_rgb(width, height, &rgb_data);
-        frame.delay = frame_duration / 10; // GIF delay is in centiseconds
+        let mut gif_frame = Frame::from_rgb(self.width, self.height, &self.rgb_data);
+        gif_frame.delay = (frame.delay.as_millis() / 10) as u16; // GIF delay is in centiseconds
 
-        encoder
-            .write_frame(&frame)
+        self.encoder
+            .write_frame(&gif_frame)
             .with_context(|| format!("Failed to write frame {}", i + 1))?;
+        self.written += 1;
+        Ok(())
     }
 
-    Ok(())
+    fn finish(self) -> Result<()> {
+        if self.written == 0 {
+            anyhow::bail!("No frames to write");
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -310,7 +843,8 @@ mod tests {
         let beam = ElectronBeamBuilder::new()
             .dimensions(10, 10)
             .mode(AnimationMode::CoolDown)
-            .build();
+            .build()
+            .unwrap();
 
         // Create a simple test image
         let test_image =
@@ -319,7 +853,7 @@ mod tests {
         let mut beam = beam;
         beam.prepare(test_image).unwrap();
 
-        let frames = generate_frames(&beam, 5, false).unwrap();
+        let frames: Vec<_> = beam.frames(5).map(|f| f.unwrap().image).collect();
         assert_eq!(frames.len(), 5);
 
         // Check that all frames have the correct dimensions
@@ -334,7 +868,8 @@ mod tests {
         let beam = ElectronBeamBuilder::new()
             .dimensions(10, 10)
             .mode(AnimationMode::CoolDown)
-            .build();
+            .build()
+            .unwrap();
 
         let test_image =
             image::ImageBuffer::from_fn(10, 10, |_, _| image::Rgba([255, 255, 255, 255]));
@@ -342,8 +877,12 @@ mod tests {
         let mut beam = beam;
         beam.prepare(test_image).unwrap();
 
-        let normal_frames = generate_frames(&beam, 3, false).unwrap();
-        let reverse_frames = generate_frames(&beam, 3, true).unwrap();
+        let normal_frames: Vec<_> = beam.frames(3).map(|f| f.unwrap().image).collect();
+        let reverse_frames: Vec<_> = beam
+            .frames(3)
+            .reversed()
+            .map(|f| f.unwrap().image)
+            .collect();
 
         assert_eq!(normal_frames.len(), reverse_frames.len());
 
@@ -355,14 +894,357 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_resolution() {
+        assert_eq!(parse_resolution("320x240").unwrap(), (320, 240));
+        assert!(parse_resolution("320").is_err());
+        assert!(parse_resolution("axb").is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("ff8000").unwrap(), [255, 128, 0]);
+        assert_eq!(parse_color("#00FF7f").unwrap(), [0, 255, 127]);
+        assert!(parse_color("fff").is_err());
+        assert!(parse_color("gg0000").is_err());
+        assert!(parse_color("ééé").is_err());
+        assert!(parse_color("+f+f+f").is_err());
+    }
+
+    #[test]
+    fn test_inspect_table() {
+        let args = Cli::parse_explicit([
+            "electron-beam",
+            "-f",
+            "5",
+            "--width",
+            "64",
+            "--height",
+            "48",
+            "inspect",
+        ])
+        .unwrap();
+        assert!(matches!(args.command, Some(Command::Inspect)));
+        assert_eq!(output_dimensions(&args, None).unwrap(), (64, 48));
+
+        let beam = beam_builder(&args, ElectronBeamConfig::default(), (64, 48))
+            .build()
+            .unwrap();
+        let table = inspect_table(&beam, args.frames, false).unwrap();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[0].starts_with("frame   level  phase"));
+        assert!(lines[2].contains("vstretch"));
+        assert!(lines[4].contains("hstretch"));
+        assert!(lines[5].contains("1.000  hstretch"));
+    }
+
+    #[test]
+    fn test_web_export() {
+        assert_eq!(web_format(Path::new("out/beam.CSS")), Some(WebFormat::Css));
+        assert_eq!(web_format(Path::new("beam.svg")), Some(WebFormat::Svg));
+        assert_eq!(web_format(Path::new("beam.gif")), None);
+
+        let args = Cli::parse_explicit([
+            "electron-beam",
+            "-i",
+            "screen.png",
+            "-o",
+            "beam.css",
+            "-f",
+            "20",
+            "-d",
+            "50",
+            "--loop-animation",
+        ])
+        .unwrap();
+        let beam = beam_builder(&args, ElectronBeamConfig::default(), (64, 48))
+            .build()
+            .unwrap();
+        let css = web_export(&beam, &args, WebFormat::Css).unwrap();
+        assert!(css.contains(r#"url("screen.png")"#));
+        assert!(css.contains("animation: electron-beam-screen 1s linear infinite;"));
+
+        let args = Cli::parse_explicit([
+            "electron-beam",
+            "-i",
+            "screen.png",
+            "-o",
+            "beam.svg",
+            "--image-url",
+            "/static/screen.png",
+        ])
+        .unwrap();
+        let svg = web_export(&beam, &args, WebFormat::Svg).unwrap();
+        assert!(svg.contains(r#"href="/static/screen.png""#));
+        assert!(svg.contains(r#"dur="3s""#));
+    }
+
+    #[test]
+    fn test_output_dimensions() {
+        let parse = |extra: &[&str]| {
+            let mut line = vec!["electron-beam", "-i", "logo.png"];
+            line.extend_from_slice(extra);
+            line.push("inspect");
+            Cli::parse_explicit(line).unwrap()
+        };
+
+        // Inspecting uses the frame size the render would
+        assert_eq!(
+            output_dimensions(&parse(&[]), Some((320, 200))).unwrap(),
+            (320, 200)
+        );
+        assert_eq!(
+            output_dimensions(&parse(&["--width", "640"]), Some((320, 200))).unwrap(),
+            (640, 400)
+        );
+        assert_eq!(
+            output_dimensions(&parse(&["--height", "100"]), Some((320, 200))).unwrap(),
+            (160, 100)
+        );
+        assert!(output_dimensions(&parse(&["--width", "640"]), None).is_err());
+    }
+
+    #[test]
+    fn test_timeline_argument() {
+        let spec = "hold:0.1,vstretch:0.4,hstretch:0.4,dot:0.1";
+        let args = Cli::parse_explicit(["electron-beam", "-i", "a", "-o", "b", "--timeline", spec])
+            .unwrap();
+        let beam = beam_builder(&args, ElectronBeamConfig::default(), (64, 48))
+            .build()
+            .unwrap();
+        assert_eq!(beam.config().timeline.as_ref().unwrap().to_string(), spec);
+
+        let bad = Cli::parse_explicit([
+            "electron-beam",
+            "-i",
+            "a",
+            "-o",
+            "b",
+            "--timeline",
+            "warp:1",
+        ]);
+        assert!(bad.is_err());
+    }
+
+    #[test]
+    fn test_create_gif_streams_frames() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(12, 8)
+            .build()
+            .unwrap();
+        beam.prepare(image::ImageBuffer::from_pixel(
+            12,
+            8,
+            image::Rgba([200, 100, 50, 255]),
+        ))
+        .unwrap();
+
+        let output = NamedTempFile::new().unwrap();
+        let frames = beam.frames(4).delay(Duration::from_millis(70));
+        create_gif(frames, (12, 8), &output.path().to_path_buf(), false).unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(output.path()).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+        assert_eq!(delays, [7, 7, 7, 7]);
+    }
+
+    #[test]
+    fn test_parallel_gif_matches_sequential() {
+        let mut beam = ElectronBeamBuilder::new()
+            .dimensions(12, 8)
+            .build()
+            .unwrap();
+        beam.prepare(image::ImageBuffer::from_fn(12, 8, |x, y| {
+            image::Rgba([(x * 20) as u8, (y * 30) as u8, 90, 255])
+        }))
+        .unwrap();
+
+        let sequential = NamedTempFile::new().unwrap();
+        create_gif(
+            beam.frames(9),
+            (12, 8),
+            &sequential.path().to_path_buf(),
+            false,
+        )
+        .unwrap();
+
+        let parallel = NamedTempFile::new().unwrap();
+        let mut writer = GifWriter::create(&parallel.path().to_path_buf(), (12, 8), false).unwrap();
+        beam.frames(9)
+            .render_parallel(3, |frame| Ok(writer.write(&frame)?))
+            .unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(
+            std::fs::read(sequential.path()).unwrap(),
+            std::fs::read(parallel.path()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_config_file_merges_with_flags() {
+        let mut file = tempfile::Builder::new().suffix(".toml").tempfile().unwrap();
+        std::io::Write::write_all(
+            &mut file,
+            br#"
+            frames = 12
+            width = 320
+            mode = "warm-up"
+            v_stretch_duration = 0.4
+            h_stretch_duration = 0.6
+            phosphor = "green"
+
+            [tube]
+            width = 160
+            height = 120
+            scanline_intensity = 0.5
+            "#,
+        )
+        .unwrap();
+        let config = file.path().to_str().unwrap();
+
+        let mut args = Cli::parse_explicit([
+            "electron-beam",
+            "-i",
+            "in.png",
+            "-o",
+            "out.gif",
+            "--config",
+            config,
+            "-f",
+            "20",
+            "--v-stretch",
+            "0.3",
+            "--h-stretch",
+            "0.7",
+        ])
+        .unwrap();
+        let base = load_base_config(&mut args).unwrap();
+        let beam = beam_builder(&args, base, (320, 240)).build().unwrap();
+        let merged = beam.config();
+
+        // Flags win over the file, the file wins over flag defaults
+        assert_eq!(args.frames, 20);
+        assert_eq!(args.width, Some(320));
+        assert_eq!(args.duration, 100);
+        assert_eq!(merged.mode, AnimationMode::WarmUp);
+        assert_eq!(merged.v_stretch_duration, 0.3);
+        assert_eq!(merged.phosphor, Some(Phosphor::Green));
+        assert_eq!(
+            merged.tube.map(|t| (t.width, t.scanline_intensity)),
+            Some((160, 0.5))
+        );
+        assert_eq!(merged.highlight_strength, 1.0.into());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_config_clears_preset_settings() {
+        let config_args = |file: &tempfile::NamedTempFile| {
+            Cli::parse_explicit([
+                "electron-beam",
+                "-i",
+                "in.png",
+                "-o",
+                "out.gif",
+                "--preset",
+                "arcade",
+                "--config",
+                file.path().to_str().unwrap(),
+            ])
+            .unwrap()
+        };
+        let json_file = |text: &str| {
+            let mut file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+            std::io::Write::write_all(&mut file, text.as_bytes()).unwrap();
+            file
+        };
+
+        // The library's own output loads, nulls included
+        let saved = ElectronBeamConfig {
+            mode: AnimationMode::WarmUp,
+            ..ElectronBeamConfig::default()
+        };
+        let file = json_file(&saved.to_json().unwrap());
+        let base = load_base_config(&mut config_args(&file)).unwrap();
+        assert_eq!(format!("{base:?}"), format!("{saved:?}"));
+
+        // A null clears an option the preset sets, leaving the others
+        let file = json_file(r#"{ "tube": null }"#);
+        let base = load_base_config(&mut config_args(&file)).unwrap();
+        assert_eq!(base.tube, None);
+        assert_eq!(base.beam_thickness, Some(0.01));
+    }
+
+    #[test]
+    fn test_preset_layers_under_flags() {
+        let args = Cli::parse_explicit([
+            "electron-beam",
+            "-i",
+            "in.png",
+            "-o",
+            "out.gif",
+            "--preset",
+            "arcade",
+            "--scanlines",
+            "0.1",
+            "--background",
+            "101820",
+        ])
+        .unwrap();
+        let base = load_base_config(&mut args.clone()).unwrap();
+        let beam = beam_builder(&args, base, (640, 480)).build().unwrap();
+        let config = beam.config();
+
+        // Preset settings survive flag defaults, explicit flags override them
+        assert_eq!(config.v_stretch_duration, 0.4);
+        assert_eq!(config.beam_thickness, Some(0.01));
+        assert_eq!(
+            config.tube.map(|t| (t.width, t.scanline_intensity)),
+            Some((320, 0.1))
+        );
+        assert_eq!(config.chroma_split, 1.5.into());
+        assert_eq!(config.background, [16, 24, 32].into());
+
+        assert!(
+            Cli::parse_explicit(["electron-beam", "-i", "a", "-o", "b", "--preset", "nope"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_presets_list_command() {
+        let args = Cli::parse_explicit(["electron-beam", "presets", "list"]).unwrap();
+        assert!(matches!(
+            args.command,
+            Some(Command::Presets {
+                action: PresetsAction::List
+            })
+        ));
+        assert!(Cli::parse_explicit(["electron-beam"]).is_err());
+
+        let listing = list_presets();
+        assert_eq!(listing.lines().count(), PRESETS.len());
+        assert!(listing.starts_with("android-classic  The Android turn-off"));
+    }
+
     #[test]
     fn test_validate_arguments() {
         let temp_file = NamedTempFile::new().unwrap();
         let temp_path = temp_file.path().to_path_buf();
 
         let valid_args = Cli {
-            input: temp_path,
-            output: PathBuf::from("test.gif"),
+            command: None,
+            input: Some(temp_path),
+            output: Some(PathBuf::from("test.gif")),
+            preset: None,
             mode: CliAnimationMode::CoolDown,
             frames: 10,
             duration: 100,
@@ -370,10 +1252,30 @@ mod tests {
             height: None,
             v_stretch: 0.5,
             h_stretch: 0.5,
+            pixel_aspect: 1.0,
+            tube: None,
+            scanlines: 0.0,
+            composite: false,
+            phosphor: None,
+            beam_color: None,
+            beam_kelvin: None,
+            beam_thickness: None,
+            highlight_tint: [255, 255, 255],
+            highlight_strength: 1.0,
+            chroma_split: 1.0,
+            background: [0, 0, 0],
+            threads: 0,
+            jobs: 0,
+            exact_scale: false,
+            timeline: None,
             verbose: false,
             debug: false,
             reverse: false,
             loop_animation: false,
+            image_url: None,
+            #[cfg(feature = "serde")]
+            config: None,
+            explicit: HashSet::new(),
         };
 
         assert!(validate_arguments(&valid_args).is_ok());
@@ -383,13 +1285,35 @@ mod tests {
         invalid_args.frames = 0;
         assert!(validate_arguments(&invalid_args).is_err());
 
-        // Test invalid stretch values
+        // Animation settings are checked by the library
+        assert!(
+            beam_builder(&valid_args, ElectronBeamConfig::default(), (640, 480))
+                .build()
+                .is_ok()
+        );
+
         invalid_args = valid_args.clone();
         invalid_args.h_stretch = -0.1;
-        assert!(validate_arguments(&invalid_args).is_err());
+        assert!(
+            beam_builder(&invalid_args, ElectronBeamConfig::default(), (640, 480))
+                .build()
+                .is_err()
+        );
 
         invalid_args = valid_args.clone();
         invalid_args.v_stretch = 1.1;
-        assert!(validate_arguments(&invalid_args).is_err());
+        assert!(
+            beam_builder(&invalid_args, ElectronBeamConfig::default(), (640, 480))
+                .build()
+                .is_err()
+        );
+
+        invalid_args = valid_args.clone();
+        invalid_args.tube = Some((0, 240));
+        assert!(
+            beam_builder(&invalid_args, ElectronBeamConfig::default(), (640, 480))
+                .build()
+                .is_err()
+        );
     }
 }
@@ -0,0 +1,164 @@
+//! Mip pyramid for fast scaled sampling
+//!
+//! The scale-down effect shrinks the whole source on every frame. Resizing
+//! with a Lanczos filter each time is slow, so the source is instead reduced
+//! once into a chain of half-size levels that frames sample trilinearly.
+
+use image::RgbaImage;
+
+/// Chain of successively halved copies of an image, down to 1x1
+///
+/// The image itself is level 0 and is not copied: it is passed back to
+/// [`sample`](Self::sample) along with the coordinates.
+#[derive(Debug, Clone)]
+pub(crate) struct MipPyramid {
+    /// Levels 1 and up
+    levels: Vec<RgbaImage>,
+}
+
+impl MipPyramid {
+    /// Build the pyramid, each level a 2x2 box-filtered copy of the previous one
+    ///
+    /// Odd sizes round up, so the last column or row of a level is filtered
+    /// on its own instead of being dropped.
+    pub(crate) fn build(source: &RgbaImage) -> Self {
+        let mut levels: Vec<RgbaImage> = Vec::new();
+
+        loop {
+            let last = levels.last().unwrap_or(source);
+            let (width, height) = last.dimensions();
+            if width <= 1 && height <= 1 {
+                break;
+            }
+
+            let (half_width, half_height) = (width - width / 2, height - height / 2);
+            let next = RgbaImage::from_fn(half_width, half_height, |x, y| {
+                let xs = [2 * x, (2 * x + 1).min(width - 1)];
+                let ys = [2 * y, (2 * y + 1).min(height - 1)];
+                let mut sum = [0u16; 4];
+                for sy in ys {
+                    for sx in xs {
+                        let pixel = last.get_pixel(sx, sy);
+                        for i in 0..4 {
+                            sum[i] += pixel[i] as u16;
+                        }
+                    }
+                }
+                image::Rgba(sum.map(|s| ((s + 2) / 4) as u8))
+            });
+            levels.push(next);
+        }
+
+        Self { levels }
+    }
+
+    /// Number of levels, the source included
+    pub(crate) fn len(&self) -> usize {
+        self.levels.len() + 1
+    }
+
+    /// Sample at a position in full-resolution pixel coordinates
+    ///
+    /// `source` is the image the pyramid was built from. `lod` is the base-2
+    /// logarithm of the minification: 0 samples the source itself, 1 the
+    /// half-size level, and fractions blend the two nearest levels. Channels
+    /// are returned in the 0..255 range.
+    pub(crate) fn sample(&self, source: &RgbaImage, u: f32, v: f32, lod: f32) -> [f32; 4] {
+        let lod = lod.clamp(0.0, self.levels.len() as f32);
+        let level = lod.floor() as usize;
+        let t = lod - level as f32;
+
+        let near = self.bilinear(source, level, u, v);
+        if t <= 0.0 {
+            return near;
+        }
+
+        let far = self.bilinear(source, level + 1, u, v);
+        std::array::from_fn(|i| near[i] + (far[i] - near[i]) * t)
+    }
+
+    /// Bilinearly sample one level, clamping to its edges
+    fn bilinear(&self, source: &RgbaImage, level: usize, u: f32, v: f32) -> [f32; 4] {
+        let image = match level {
+            0 => source,
+            _ => &self.levels[level - 1],
+        };
+        let (width, height) = image.dimensions();
+
+        // Map full-resolution coordinates to texel centres of this level
+        let x = (u * width as f32 / source.width() as f32 - 0.5).clamp(0.0, (width - 1) as f32);
+        let y = (v * height as f32 / source.height() as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+
+        let [p00, p10, p01, p11] = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)]
+            .map(|(px, py)| image.get_pixel(px, py).0.map(|c| c as f32));
+        std::array::from_fn(|i| {
+            let top = p00[i] + (p10[i] - p00[i]) * tx;
+            let bottom = p01[i] + (p11[i] - p01[i]) * tx;
+            top + (bottom - top) * ty
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_pyramid_levels() {
+        let pyramid = MipPyramid::build(&RgbaImage::new(10, 3));
+        let sizes: Vec<_> = pyramid.levels.iter().map(|l| l.dimensions()).collect();
+        assert_eq!(sizes, [(5, 2), (3, 1), (2, 1), (1, 1)]);
+        assert_eq!(pyramid.len(), 5);
+    }
+
+    #[test]
+    fn test_odd_sizes_keep_the_edges() {
+        // A one pixel border must show up at every level
+        let source = RgbaImage::from_fn(9, 7, |x, y| match x == 0 || y == 0 || x == 8 || y == 6 {
+            true => Rgba([255, 255, 255, 255]),
+            false => Rgba([0, 0, 0, 255]),
+        });
+        let pyramid = MipPyramid::build(&source);
+
+        for level in &pyramid.levels {
+            let (width, height) = level.dimensions();
+            for y in 0..height {
+                assert!(level.get_pixel(width - 1, y)[0] > 0, "{width}x{height}");
+            }
+            for x in 0..width {
+                assert!(level.get_pixel(x, height - 1)[0] > 0, "{width}x{height}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_sample_level_zero_is_exact() {
+        let source =
+            RgbaImage::from_fn(8, 8, |x, y| Rgba([(x * 30) as u8, (y * 30) as u8, 7, 255]));
+        let pyramid = MipPyramid::build(&source);
+
+        for (x, y, pixel) in source.enumerate_pixels() {
+            let sample = pyramid.sample(&source, x as f32 + 0.5, y as f32 + 0.5, 0.0);
+            assert_eq!(sample.map(|c| c.round() as u8), pixel.0);
+        }
+    }
+
+    #[test]
+    fn test_coarse_level_averages() {
+        let source = RgbaImage::from_fn(4, 4, |x, _| {
+            if x % 2 == 0 {
+                Rgba([0, 0, 0, 255])
+            } else {
+                Rgba([200, 200, 200, 255])
+            }
+        });
+        let pyramid = MipPyramid::build(&source);
+
+        let sample = pyramid.sample(&source, 2.0, 2.0, 2.0);
+        assert!((sample[0] - 100.0).abs() < 1.0);
+    }
+}
@@ -0,0 +1,141 @@
+//! Prepared animations
+//!
+//! [`PreparedBeam`] can only be obtained by preparing an animation with a
+//! source image, so drawing an unprepared effect is a compile error instead
+//! of an [`ElectronBeamError::NotPrepared`](crate::ElectronBeamError) at run
+//! time.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use image::RgbaImage;
+
+use crate::{ElectronBeam, ElectronBeamConfig, Frames, Result};
+
+/// An animation that has been prepared with a source image
+///
+/// Created by [`ElectronBeamBuilder::prepare`](crate::ElectronBeamBuilder::prepare)
+/// or [`ElectronBeam::into_prepared`]. The resized source is reference
+/// counted, so clones are cheap and can be sent to other threads to draw
+/// frames concurrently.
+#[derive(Clone)]
+pub struct PreparedBeam {
+    beam: ElectronBeam,
+}
+
+impl PreparedBeam {
+    /// Wrap a beam that `prepare` has just succeeded on
+    pub(crate) fn new(beam: ElectronBeam) -> Self {
+        debug_assert!(beam.is_prepared());
+        Self { beam }
+    }
+
+    /// The configuration the animation was prepared with
+    pub fn config(&self) -> &ElectronBeamConfig {
+        self.beam.config()
+    }
+
+    /// The source image, resized to the render dimensions
+    pub fn source(&self) -> &Arc<RgbaImage> {
+        self.beam
+            .source_image
+            .as_ref()
+            .expect("a prepared beam always has a source")
+    }
+
+    /// Generate a frame at the specified animation level (0.0 to 1.0)
+    pub fn draw(&self, level: f32) -> Result<RgbaImage> {
+        self.beam.draw(level)
+    }
+
+    /// Draw a frame into a caller-owned image of the output dimensions
+    pub fn draw_into(&self, level: f32, output: &mut RgbaImage) -> Result<()> {
+        self.beam.draw_into(level, output)
+    }
+
+    /// Draw a frame into a raw RGBA8 buffer with rows `stride` bytes apart
+    pub fn draw_into_raw(&self, level: f32, data: &mut [u8], stride: usize) -> Result<()> {
+        self.beam.draw_into_raw(level, data, stride)
+    }
+
+    /// Lazily render `count` frames spread evenly over the animation
+    pub fn frames(&self, count: u32) -> Frames<'_> {
+        self.beam.frames(count)
+    }
+
+    /// Lazily render the animation at `fps` frames per second over `duration`
+    pub fn frames_at(&self, fps: f32, duration: Duration) -> Frames<'_> {
+        self.beam.frames_at(fps, duration)
+    }
+
+    /// The same animation prepared with a new source image
+    ///
+    /// Keeps the render thread pool, unlike building the animation again.
+    pub fn with_source(self, image: RgbaImage) -> Result<Self> {
+        self.beam.into_prepared(image)
+    }
+
+    /// The underlying beam, which is always prepared
+    pub(crate) fn as_beam(&self) -> &ElectronBeam {
+        &self.beam
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnimationMode, ElectronBeamBuilder};
+    use image::Rgba;
+
+    fn source() -> RgbaImage {
+        RgbaImage::from_fn(20, 10, |x, y| {
+            Rgba([(x * 12) as u8, (y * 25) as u8, 60, 255])
+        })
+    }
+
+    #[test]
+    fn test_prepared_matches_beam() {
+        let builder = ElectronBeamBuilder::new()
+            .dimensions(20, 10)
+            .mode(AnimationMode::ScaleDown);
+
+        let mut beam = builder.clone().build().unwrap();
+        beam.prepare(source()).unwrap();
+        let prepared = builder.prepare(source()).unwrap();
+
+        for level in [0.0, 0.3, 0.8, 1.0] {
+            assert_eq!(prepared.draw(level).unwrap(), beam.draw(level).unwrap());
+        }
+        assert!(prepared.draw(1.5).is_err());
+
+        let inverted = RgbaImage::from_fn(20, 10, |x, y| {
+            Rgba([(y * 25) as u8, (x * 12) as u8, 60, 255])
+        });
+        beam.prepare(inverted.clone()).unwrap();
+        let prepared = prepared.with_source(inverted).unwrap();
+        assert_eq!(prepared.draw(0.3).unwrap(), beam.draw(0.3).unwrap());
+    }
+
+    #[test]
+    fn test_prepared_source_is_shared() {
+        let prepared = ElectronBeamBuilder::new()
+            .dimensions(20, 10)
+            .prepare(source())
+            .unwrap();
+        let expected = prepared.draw(0.25).unwrap();
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| {
+                    let clone = prepared.clone();
+                    assert!(Arc::ptr_eq(clone.source(), prepared.source()));
+                    scope.spawn(move || clone.draw(0.25).unwrap())
+                })
+                .collect();
+
+            for handle in handles {
+                assert_eq!(handle.join().unwrap(), expected);
+            }
+        });
+    }
+}
@@ -0,0 +1,181 @@
+//! Named presets
+//!
+//! Ready-made configurations bundling a mode and post effects, with the
+//! stretch timings and channel spread the look calls for, as a starting
+//! point that looks good without any tuning.
+
+use crate::{
+    AnimationMode, BeamColor, CompositeConfig, ElectronBeamConfig, ElectronBeamError, Phosphor,
+    Result, TubeResolution,
+};
+
+/// A named configuration
+#[derive(Debug, Clone, Copy)]
+pub struct Preset {
+    /// Kebab-case name, as accepted by [`ElectronBeamConfig::preset`]
+    pub name: &'static str,
+    /// One-line description of the look
+    pub description: &'static str,
+    config: fn() -> ElectronBeamConfig,
+}
+
+impl Preset {
+    /// The configuration of the preset
+    pub fn config(&self) -> ElectronBeamConfig {
+        (self.config)()
+    }
+}
+
+/// Every built-in preset
+pub const PRESETS: &[Preset] = &[
+    Preset {
+        name: "android-classic",
+        description:
+            "The Android turn-off exactly as the Java original: even v/h split, white beam",
+        config: ElectronBeamConfig::android_classic,
+    },
+    Preset {
+        name: "trinitron",
+        description: "Crisp aperture-grille set: fast, tightly converged collapse, thin 9300K beam",
+        config: ElectronBeamConfig::trinitron,
+    },
+    Preset {
+        name: "arcade",
+        description: "320x240 cabinet monitor with heavy scanlines and a bold beam",
+        config: ElectronBeamConfig::arcade,
+    },
+    Preset {
+        name: "terminal-green",
+        description: "P1 green phosphor terminal with faint scanlines",
+        config: ElectronBeamConfig::terminal_green,
+    },
+    Preset {
+        name: "vhs-tv",
+        description:
+            "Living-room TV on a composite feed: colour bleed, misconvergence, low resolution",
+        config: ElectronBeamConfig::vhs_tv,
+    },
+    Preset {
+        name: "subtle-ui",
+        description: "Gentle scale-down for app transitions, no CRT artifacts",
+        config: ElectronBeamConfig::subtle_ui,
+    },
+];
+
+impl ElectronBeamConfig {
+    /// Look up a built-in preset by name
+    pub fn preset(name: &str) -> Result<Self> {
+        PRESETS
+            .iter()
+            .find(|preset| preset.name == name)
+            .map(Preset::config)
+            .ok_or_else(|| ElectronBeamError::UnknownPreset(name.to_string()))
+    }
+
+    /// The Android turn-off with the Java original's timings and curves
+    pub fn android_classic() -> Self {
+        Self {
+            mode: AnimationMode::CoolDown,
+            v_stretch_duration: 0.5,
+            h_stretch_duration: 0.5,
+            chroma_split: 1.0.into(),
+            ..Self::default()
+        }
+    }
+
+    /// A Sony Trinitron style set with a cool white point
+    pub fn trinitron() -> Self {
+        Self {
+            mode: AnimationMode::CoolDown,
+            v_stretch_duration: 0.35,
+            h_stretch_duration: 0.65,
+            beam_color: BeamColor::Kelvin(9300.0),
+            beam_thickness: Some(0.004),
+            highlight_tint: [200, 215, 255].into(),
+            highlight_strength: 0.8.into(),
+            // Tight convergence keeps the channel curves close together
+            chroma_split: 0.5.into(),
+            ..Self::default()
+        }
+    }
+
+    /// A low-resolution arcade monitor
+    pub fn arcade() -> Self {
+        Self {
+            mode: AnimationMode::CoolDown,
+            v_stretch_duration: 0.4,
+            h_stretch_duration: 0.6,
+            tube: Some(TubeResolution {
+                scanline_intensity: 0.5,
+                ..TubeResolution::new(320, 240)
+            }),
+            beam_thickness: Some(0.01),
+            chroma_split: 1.5.into(),
+            ..Self::default()
+        }
+    }
+
+    /// A green monochrome terminal
+    pub fn terminal_green() -> Self {
+        Self {
+            mode: AnimationMode::CoolDown,
+            tube: Some(TubeResolution {
+                scanline_intensity: 0.25,
+                ..TubeResolution::new(640, 400)
+            }),
+            phosphor: Some(Phosphor::Green),
+            ..Self::default()
+        }
+    }
+
+    /// A consumer TV fed by a VCR over composite video
+    pub fn vhs_tv() -> Self {
+        Self {
+            mode: AnimationMode::CoolDown,
+            v_stretch_duration: 0.55,
+            h_stretch_duration: 0.45,
+            tube: Some(TubeResolution {
+                scanline_intensity: 0.2,
+                ..TubeResolution::new(320, 240)
+            }),
+            composite: Some(CompositeConfig {
+                chroma_blur: 3,
+                chroma_bleed: 2.0,
+                ..CompositeConfig::default()
+            }),
+            beam_color: BeamColor::Kelvin(6500.0),
+            highlight_strength: 0.7.into(),
+            // A worn set with poor convergence
+            chroma_split: 2.5.into(),
+            ..Self::default()
+        }
+    }
+
+    /// An understated transition for user interfaces
+    pub fn subtle_ui() -> Self {
+        Self {
+            mode: AnimationMode::ScaleDown,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_valid() {
+        for preset in PRESETS {
+            let config = ElectronBeamConfig::preset(preset.name).unwrap();
+            assert!(config.validate().is_ok(), "{} is invalid", preset.name);
+        }
+
+        let names: Vec<_> = PRESETS.iter().map(|p| p.name).collect();
+        assert!(names.contains(&"android-classic"));
+        assert!(matches!(
+            ElectronBeamConfig::preset("betamax"),
+            Err(ElectronBeamError::UnknownPreset(name)) if name == "betamax"
+        ));
+    }
+}
@@ -0,0 +1,1282 @@
+//! Fragment shader generation
+//!
+//! [`ElectronBeam::fragment_shader`] writes a self-contained GLSL ES or WGSL
+//! fragment shader that draws the effect of one configuration on the GPU.
+//! Everything `draw` works out per frame from the level (the active phase,
+//! its eased progress, the curves, the quads and the animated parameters)
+//! is compiled into straight-line code of the `level` uniform. The per-pixel
+//! part is a fixed template doing what the renderer does for each phase.
+
+use crate::{
+    Animated, AnimationMode, Easing, ElectronBeam, ElectronBeamError, Phase, PreparedBeam, Result,
+};
+use std::fmt::{self, Write};
+use std::ops::{Add, Div, Mul, Sub};
+
+/// Language of a generated shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShaderLanguage {
+    /// GLSL ES 3.00, for WebGL 2 and OpenGL ES 3
+    GlslEs,
+    /// WGSL, for WebGPU and wgpu
+    Wgsl,
+}
+
+/// A scalar expression of the animation level
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Level,
+    Const(f32),
+    /// A value bound earlier in the program
+    Var(usize),
+    Exp(Box<Expr>),
+    Floor(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+    /// `then` if `a < b`, `otherwise` if not
+    Select {
+        a: Box<Expr>,
+        b: Box<Expr>,
+        then: Box<Expr>,
+        otherwise: Box<Expr>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Min,
+    Max,
+}
+
+impl Op {
+    fn apply(self, a: f32, b: f32) -> f32 {
+        match self {
+            Op::Add => a + b,
+            Op::Sub => a - b,
+            Op::Mul => a * b,
+            Op::Div => a / b,
+            Op::Min => a.min(b),
+            Op::Max => a.max(b),
+        }
+    }
+}
+
+/// A constant expression
+fn c(value: f32) -> Expr {
+    Expr::Const(value)
+}
+
+impl Expr {
+    /// Combine two expressions, folding constants the way the CPU computes them
+    fn binary(op: Op, a: Expr, b: Expr) -> Expr {
+        match (a, b) {
+            (Expr::Const(a), Expr::Const(b)) => c(op.apply(a, b)),
+            // Exact identities
+            (a, Expr::Const(b)) if b == 0.0 && matches!(op, Op::Add | Op::Sub) => a,
+            (a, Expr::Const(b)) if b == 1.0 && matches!(op, Op::Mul | Op::Div) => a,
+            (Expr::Const(a), b) if a == 1.0 && op == Op::Mul => b,
+            (a, b) => Expr::Binary(op, Box::new(a), Box::new(b)),
+        }
+    }
+
+    fn min(self, other: Expr) -> Expr {
+        Expr::binary(Op::Min, self, other)
+    }
+
+    fn max(self, other: Expr) -> Expr {
+        Expr::binary(Op::Max, self, other)
+    }
+
+    fn clamp(self, min: f32, max: f32) -> Expr {
+        self.max(c(min)).min(c(max))
+    }
+
+    fn exp(self) -> Expr {
+        match self {
+            Expr::Const(value) => c(value.exp()),
+            other => Expr::Exp(Box::new(other)),
+        }
+    }
+
+    fn floor(self) -> Expr {
+        match self {
+            Expr::Const(value) => c(value.floor()),
+            other => Expr::Floor(Box::new(other)),
+        }
+    }
+
+    /// `then` where `a < b`, otherwise `otherwise`
+    fn select(a: Expr, b: Expr, then: Expr, otherwise: Expr) -> Expr {
+        match (&a, &b) {
+            (Expr::Const(a), Expr::Const(b)) => {
+                if a < b {
+                    then
+                } else {
+                    otherwise
+                }
+            }
+            _ => Expr::Select {
+                a: Box::new(a),
+                b: Box::new(b),
+                then: Box::new(then),
+                otherwise: Box::new(otherwise),
+            },
+        }
+    }
+
+    #[cfg(test)]
+    fn eval(&self, level: f32, values: &[f32]) -> f32 {
+        match self {
+            Expr::Level => level,
+            Expr::Const(value) => *value,
+            Expr::Var(index) => values[*index],
+            Expr::Exp(x) => x.eval(level, values).exp(),
+            Expr::Floor(x) => x.eval(level, values).floor(),
+            Expr::Binary(op, a, b) => op.apply(a.eval(level, values), b.eval(level, values)),
+            Expr::Select {
+                a,
+                b,
+                then,
+                otherwise,
+            } => {
+                if a.eval(level, values) < b.eval(level, values) {
+                    then.eval(level, values)
+                } else {
+                    otherwise.eval(level, values)
+                }
+            }
+        }
+    }
+}
+
+impl Add for Expr {
+    type Output = Expr;
+    fn add(self, other: Expr) -> Expr {
+        Expr::binary(Op::Add, self, other)
+    }
+}
+
+impl Sub for Expr {
+    type Output = Expr;
+    fn sub(self, other: Expr) -> Expr {
+        Expr::binary(Op::Sub, self, other)
+    }
+}
+
+impl Mul for Expr {
+    type Output = Expr;
+    fn mul(self, other: Expr) -> Expr {
+        Expr::binary(Op::Mul, self, other)
+    }
+}
+
+impl Div for Expr {
+    type Output = Expr;
+    fn div(self, other: Expr) -> Expr {
+        Expr::binary(Op::Div, self, other)
+    }
+}
+
+/// Named values computed from the level, in order
+#[derive(Debug, Default)]
+struct Program {
+    bindings: Vec<(String, Expr)>,
+}
+
+impl Program {
+    /// Bind an expression to a name, returning a reference to it
+    ///
+    /// Constants are returned as they are so that later expressions fold them.
+    fn bind(&mut self, name: impl Into<String>, expr: Expr) -> Expr {
+        let reference = match expr {
+            Expr::Const(_) => expr.clone(),
+            _ => Expr::Var(self.bindings.len()),
+        };
+        self.bindings.push((name.into(), expr));
+        reference
+    }
+
+    /// Values of every binding at a level
+    #[cfg(test)]
+    fn eval(&self, level: f32) -> Vec<f32> {
+        let mut values = Vec::with_capacity(self.bindings.len());
+        for (_, expr) in &self.bindings {
+            values.push(expr.eval(level, &values));
+        }
+        values
+    }
+
+    fn write(&self, out: &mut String, language: ShaderLanguage) -> fmt::Result {
+        for (name, expr) in &self.bindings {
+            let expr = self.emit(expr, language);
+            match language {
+                ShaderLanguage::GlslEs => writeln!(out, "    float {name} = {expr};")?,
+                ShaderLanguage::Wgsl => writeln!(out, "    let {name} = {expr};")?,
+            }
+        }
+        Ok(())
+    }
+
+    fn emit(&self, expr: &Expr, language: ShaderLanguage) -> String {
+        let emit = |expr: &Expr| self.emit(expr, language);
+        match expr {
+            Expr::Level => "level".to_string(),
+            Expr::Const(value) if value.is_sign_negative() => format!("({value:?})"),
+            Expr::Const(value) => format!("{value:?}"),
+            Expr::Var(index) => self.bindings[*index].0.clone(),
+            Expr::Exp(x) => format!("exp({})", emit(x)),
+            Expr::Floor(x) => format!("floor({})", emit(x)),
+            Expr::Binary(op, a, b) => {
+                let (a, b) = (emit(a), emit(b));
+                match op {
+                    Op::Add => format!("({a} + {b})"),
+                    Op::Sub => format!("({a} - {b})"),
+                    Op::Mul => format!("({a} * {b})"),
+                    Op::Div => format!("({a} / {b})"),
+                    Op::Min => format!("min({a}, {b})"),
+                    Op::Max => format!("max({a}, {b})"),
+                }
+            }
+            Expr::Select {
+                a,
+                b,
+                then,
+                otherwise,
+            } => {
+                let (a, b, then, otherwise) = (emit(a), emit(b), emit(then), emit(otherwise));
+                match language {
+                    ShaderLanguage::GlslEs => format!("({a} < {b} ? {then} : {otherwise})"),
+                    ShaderLanguage::Wgsl => format!("select({otherwise}, {then}, {a} < {b})"),
+                }
+            }
+        }
+    }
+}
+
+/// Apply an easing to a bound progress value
+fn ease(easing: Easing, t: Expr) -> Expr {
+    match easing {
+        Easing::Linear => t,
+        Easing::EaseIn => t.clone() * t,
+        Easing::EaseOut => t.clone() * (c(2.0) - t),
+        Easing::EaseInOut => t.clone() * t.clone() * (c(3.0) - c(2.0) * t),
+    }
+}
+
+/// The S-curve of `ElectronBeam::scurve`
+fn scurve(value: Expr, s: Expr) -> Expr {
+    let sigmoid = |x: Expr, s: Expr| c(1.0) / (c(1.0) + ((c(0.0) - x) * s).exp());
+    let y = sigmoid(value - c(0.5), s.clone()) - c(0.5);
+    let v = sigmoid(c(0.5), s) - c(0.5);
+    y / v * c(0.5) + c(0.5)
+}
+
+/// A parameter as a chain of keyframe interpolations selected by the level
+///
+/// Colours are rounded like their `Lerp` implementation.
+fn animated<T: Copy>(
+    program: &mut Program,
+    name: &str,
+    animated: &Animated<T>,
+    component: impl Fn(T) -> f32,
+    round: bool,
+) -> Expr {
+    let keyframes = match animated {
+        Animated::Constant(value) => return program.bind(name, c(component(*value))),
+        Animated::Keyframes(keyframes) => keyframes,
+    };
+
+    let last = keyframes.last().expect("validated keyframes are not empty");
+    let mut value = c(component(last.value));
+    for (i, pair) in keyframes.windows(2).enumerate().rev() {
+        let (from, to) = (&pair[0], &pair[1]);
+        let t = program.bind(
+            format!("{name}_t{i}"),
+            (Expr::Level - c(from.level)) / c(to.level - from.level),
+        );
+        let (a, b) = (component(from.value), component(to.value));
+        let mut lerped = c(a) + c(b - a) * ease(from.easing, t);
+        if round {
+            lerped = (lerped + c(0.5)).floor();
+        }
+        value = Expr::select(Expr::Level, c(to.level), lerped, value);
+    }
+    let first = &keyframes[0];
+    value = Expr::select(
+        Expr::Level,
+        c(first.level),
+        c(component(first.value)),
+        value,
+    );
+    program.bind(name, value)
+}
+
+/// Code of a phase in the generated `phase` value
+fn phase_code(phase: &Phase) -> Result<u8> {
+    Ok(match phase {
+        Phase::Hold => 0,
+        Phase::VStretch => 1,
+        Phase::HStretch => 2,
+        Phase::Dot => 3,
+        Phase::Fade => 4,
+        Phase::Scale => 5,
+        Phase::Custom { name, .. } => {
+            return Err(ElectronBeamError::UnsupportedPhase(name.clone()))
+        }
+    })
+}
+
+const CHANNELS: [&str; 3] = ["r", "g", "b"];
+
+/// Tube upscale of the render to the output
+#[derive(Debug, Clone, Copy)]
+struct TubeUpscale {
+    factor: f32,
+    offset: [f32; 2],
+    gap_scale: f32,
+}
+
+/// Values baked into the template
+#[derive(Debug, Clone)]
+struct Constants {
+    output_size: [f32; 2],
+    render_size: [f32; 2],
+    tube: Option<TubeUpscale>,
+    phosphor: Option<[f32; 3]>,
+    /// First and end row of the beam line
+    line_rows: [f32; 2],
+    /// Centre of the dot in display units
+    dot_center: [f32; 2],
+    pixel_aspect_ratio: f32,
+    /// Codes of the phases in the timeline, in increasing order
+    phases: Vec<u8>,
+}
+
+impl ElectronBeam {
+    /// Generate a fragment shader drawing the effect on the GPU
+    ///
+    /// The shader draws the frame at its `level` uniform (0.0 to 1.0) the way
+    /// [`draw`](Self::draw) does, fetching texels of the source resized to the
+    /// render size. The scale phase samples it trilinearly, so the texture
+    /// needs mipmaps when the timeline scales. Composite artifacts are not
+    /// part of the shader, and custom timeline phases fail with
+    /// `UnsupportedPhase`.
+    ///
+    /// Both languages expect the first texture row to be the top of the
+    /// image. WGSL framebuffers have their origin at the top left, so frame
+    /// rows match directly. The GLSL shader flips `gl_FragCoord.y`, which
+    /// assumes OpenGL's bottom-left origin: drawing with the default
+    /// framebuffer or an FBO read back the usual way. A target that is
+    /// flipped again afterwards, or a clip-control origin at the top left,
+    /// shows the frame upside down.
+    pub fn fragment_shader(&self, language: ShaderLanguage) -> Result<String> {
+        let program = self.frame_program()?;
+        let constants = self.shader_constants()?;
+        let mut source = String::new();
+        match language {
+            ShaderLanguage::GlslEs => write_glsl(&mut source, &program, &constants),
+            ShaderLanguage::Wgsl => write_wgsl(&mut source, &program, &constants),
+        }
+        .expect("writing to a String cannot fail");
+        Ok(source)
+    }
+
+    /// Compile what `render` works out from the level into a program
+    fn frame_program(&self) -> Result<Program> {
+        let mut program = Program::default();
+        let config = &self.config;
+
+        // Segments as `Timeline::locate` walks them
+        let mut segments = Vec::new();
+        let mut start = 0.0;
+        for segment in self.timeline.segments().iter().filter(|s| s.duration > 0.0) {
+            segments.push((segment, start, phase_code(&segment.phase)?));
+            start += segment.duration;
+        }
+
+        let position = program.bind("position", Expr::Level * c(self.timeline.total_duration()));
+        let mut located = Vec::new();
+        for (i, &(segment, start, code)) in segments.iter().enumerate() {
+            let t = program.bind(
+                format!("segment{i}_t"),
+                ((position.clone() - c(start)) / c(segment.duration)).clamp(0.0, 1.0),
+            );
+            located.push((start + segment.duration, code, ease(segment.easing, t)));
+        }
+        let (_, last_code, last_progress) =
+            located.pop().expect("a validated timeline has a phase");
+        let (phase, progress) = located.into_iter().rev().fold(
+            (c(last_code as f32), last_progress),
+            |(phase, progress), (end, code, segment_progress)| {
+                (
+                    Expr::select(position.clone(), c(end), c(code as f32), phase),
+                    Expr::select(position.clone(), c(end), segment_progress, progress),
+                )
+            },
+        );
+        program.bind("phase", phase);
+        let progress = program.bind("progress", progress);
+
+        for (i, channel) in CHANNELS.iter().enumerate() {
+            animated(
+                &mut program,
+                &format!("background_{channel}"),
+                &config.background,
+                |color| color[i] as f32,
+                true,
+            );
+        }
+
+        let (dw, dh) = self.display_size();
+        let par = config.pixel_aspect_ratio;
+        let warm_up = config.mode == AnimationMode::WarmUp;
+        let beam = self.beam_color().map(|c| c as f32);
+
+        if self.timeline.contains(&Phase::VStretch) {
+            let ag = scurve(progress.clone(), c(8.0));
+            let curves = if config.phosphor.is_some() {
+                [ag.clone(), ag.clone(), ag]
+            } else {
+                let split = c(0.5)
+                    * animated(
+                        &mut program,
+                        "chroma_split",
+                        &config.chroma_split,
+                        |s| s,
+                        false,
+                    );
+                [
+                    scurve(progress.clone(), c(8.0) - split.clone()),
+                    ag,
+                    scurve(progress.clone(), c(8.0) + split),
+                ]
+            };
+            let mut bound = Vec::new();
+            for (curve, channel) in curves.into_iter().zip(CHANNELS) {
+                let a = program.bind(format!("curve_{channel}"), curve);
+                let w = c(dw) + c(dw) * a.clone();
+                let h = c(dh) - c(dh) * a.clone();
+                program.bind(format!("quad_{channel}_x"), (c(dw) - w.clone()) * c(0.5));
+                program.bind(
+                    format!("quad_{channel}_y"),
+                    (c(dh) - h.clone()) * c(0.5) * c(par),
+                );
+                program.bind(format!("quad_{channel}_w"), w);
+                program.bind(format!("quad_{channel}_h"), h * c(par));
+                bound.push(a);
+            }
+
+            // Only the cool down adds the highlight
+            let strength = (config.mode == AnimationMode::CoolDown).then(|| {
+                animated(
+                    &mut program,
+                    "highlight_strength",
+                    &config.highlight_strength,
+                    |s| s,
+                    false,
+                ) * bound[1].clone()
+            });
+            for (i, channel) in CHANNELS.iter().enumerate() {
+                let highlight = if let Some(strength) = &strength {
+                    let tint = match config.phosphor {
+                        Some(phosphor) => c(phosphor.color()[i] as f32),
+                        None => animated(
+                            &mut program,
+                            &format!("highlight_tint_{channel}"),
+                            &config.highlight_tint,
+                            |color| color[i] as f32,
+                            true,
+                        ),
+                    };
+                    (tint * strength.clone()).min(c(255.0)).max(c(0.0)).floor()
+                } else {
+                    c(0.0)
+                };
+                program.bind(format!("highlight_{channel}"), highlight);
+            }
+        }
+
+        if self.timeline.contains(&Phase::HStretch) {
+            let (frame_width, _) = self.render_size();
+            let ag = program.bind("line_curve", scurve(progress.clone(), c(8.0)));
+            let w = c(2.0 * dw) * (c(1.0) - ag.clone());
+            let x = (c(dw) - w.clone()) * c(0.5);
+            let x_start = program.bind("line_x0", x.max(c(0.0)).floor());
+            let x_end = (x_start + w).max(c(0.0)).floor().min(c(frame_width as f32));
+            // Nothing is left to draw once fully collapsed
+            program.bind(
+                "line_x1",
+                Expr::select(progress.clone(), c(1.0), x_end, c(0.0)),
+            );
+            let intensity = c(1.0) - ag * c(0.75);
+            for (value, channel) in beam.into_iter().zip(CHANNELS) {
+                program.bind(
+                    format!("line_{channel}"),
+                    (c(value) * intensity.clone()).floor(),
+                );
+            }
+        }
+
+        if self.timeline.contains(&Phase::Dot) {
+            let fade = c(1.0) - progress.clone();
+            program.bind("dot_radius", c(self.dot_radius(0.0)) * fade.clone());
+            for (value, channel) in beam.into_iter().zip(CHANNELS) {
+                program.bind(format!("dot_{channel}"), c(value) * fade.clone());
+            }
+        }
+
+        if self.timeline.contains(&Phase::Fade) {
+            let alpha = if warm_up {
+                progress.clone()
+            } else {
+                c(1.0) - progress.clone()
+            };
+            program.bind("fade_alpha", alpha);
+        }
+
+        if self.timeline.contains(&Phase::Scale) {
+            let (frame_width, frame_height) = self.render_size();
+            let (fw, fh) = (frame_width as f32, frame_height as f32);
+            let curved = program.bind("scale_curve", scurve(progress, c(8.0)));
+            let scale = if warm_up {
+                curved.clone()
+            } else {
+                c(1.0) - curved.clone()
+            };
+            let scale = program.bind("scale", scale);
+            let w = program.bind("scale_w", (c(fw) * scale.clone()).max(c(0.0)).floor());
+            let h = program.bind("scale_h", (c(fh) * scale.clone()).max(c(0.0)).floor());
+            program.bind("scale_x", ((c(fw) - w).max(c(0.0)) * c(0.5)).floor());
+            program.bind("scale_y", ((c(fh) - h).max(c(0.0)) * c(0.5)).floor());
+            let dim = if warm_up {
+                scale
+            } else {
+                scale * (c(1.0) - curved * c(0.5))
+            };
+            program.bind("scale_dim", dim);
+        }
+
+        Ok(program)
+    }
+
+    /// Values of the template that do not depend on the level
+    fn shader_constants(&self) -> Result<Constants> {
+        let config = &self.config;
+        let (render_width, render_height) = self.render_size();
+        let tube = config.tube.map(|tube| {
+            let (factor, offset_x, offset_y) = self.tube_placement(tube);
+            TubeUpscale {
+                factor: factor as f32,
+                offset: [offset_x as f32, offset_y as f32],
+                gap_scale: 1.0 - tube.scanline_intensity.clamp(0.0, 1.0),
+            }
+        });
+        let (line, _) = self
+            .h_stretch_line(0.0)
+            .expect("the line is drawn before it collapses");
+        let (dw, dh) = self.display_size();
+
+        let mut phases = self
+            .timeline
+            .segments()
+            .iter()
+            .filter(|segment| segment.duration > 0.0)
+            .map(|segment| phase_code(&segment.phase))
+            .collect::<Result<Vec<_>>>()?;
+        phases.sort_unstable();
+        phases.dedup();
+
+        Ok(Constants {
+            output_size: [config.width as f32, config.height as f32],
+            render_size: [render_width as f32, render_height as f32],
+            tube,
+            phosphor: config
+                .phosphor
+                .map(|phosphor| phosphor.color().map(|c| c as f32)),
+            line_rows: [line.y, line.y + line.h],
+            dot_center: [dw * 0.5, dh * 0.5],
+            pixel_aspect_ratio: config.pixel_aspect_ratio,
+            phases,
+        })
+    }
+}
+
+impl PreparedBeam {
+    /// Generate a fragment shader drawing the effect, see [`ElectronBeam::fragment_shader`]
+    pub fn fragment_shader(&self, language: ShaderLanguage) -> Result<String> {
+        self.as_beam().fragment_shader(language)
+    }
+}
+
+/// A float literal valid in both languages
+fn literal(value: f32) -> String {
+    format!("{value:?}")
+}
+
+fn write_header(out: &mut String, constants: &Constants) -> fmt::Result {
+    let [ow, oh] = constants.output_size;
+    let [rw, rh] = constants.render_size;
+    writeln!(
+        out,
+        "// Electron beam fragment shader, generated by electron-beam {}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(out, "//")?;
+    writeln!(
+        out,
+        "// Draws the frame at the level uniform (0.0 to 1.0) into a {ow}x{oh} target."
+    )?;
+    writeln!(
+        out,
+        "// The source texture is the image resized to {rw}x{rh}."
+    )
+}
+
+fn write_glsl(out: &mut String, program: &Program, constants: &Constants) -> fmt::Result {
+    let [rw, rh] = constants.render_size;
+    writeln!(out, "#version 300 es")?;
+    write_header(out, constants)?;
+    writeln!(
+        out,
+        r#"precision highp float;
+precision highp sampler2D;
+
+uniform sampler2D u_source;
+uniform float u_level;
+out vec4 frag_color;
+
+const vec2 RENDER_SIZE = vec2({}, {});
+"#,
+        literal(rw),
+        literal(rh)
+    )?;
+
+    writeln!(out, "// Source colour, in 0..255\nvec4 tint(vec4 texel) {{")?;
+    match constants.phosphor {
+        Some([r, g, b]) => writeln!(
+            out,
+            "    float luma = dot(texel.rgb, vec3(0.299, 0.587, 0.114)) / 255.0;
+    return vec4(floor(vec3({}, {}, {}) * luma), texel.a);",
+            literal(r),
+            literal(g),
+            literal(b)
+        )?,
+        None => writeln!(out, "    return texel;")?,
+    }
+    writeln!(
+        out,
+        r#"}}
+
+vec4 fetch(vec2 pixel) {{
+    return tint(floor(texelFetch(u_source, ivec2(pixel), 0) * 255.0 + 0.5));
+}}
+
+bool inside(vec2 pixel, vec4 quad) {{
+    return all(greaterThanEqual(pixel, quad.xy)) && all(lessThan(pixel, quad.xy + quad.zw));
+}}
+
+// Source pixel shown at a pixel of a quad the source is stretched over
+vec2 stretched(vec2 pixel, vec4 quad) {{
+    return floor(clamp((pixel - quad.xy) / quad.zw * RENDER_SIZE, vec2(0.0), RENDER_SIZE - 1.0));
+}}
+
+void main() {{
+    // Rows counted from the top, like the frames drawn on the CPU. This
+    // assumes gl_FragCoord has its origin at the bottom left, as in OpenGL.
+    vec2 pixel = vec2(floor(gl_FragCoord.x), {} - floor(gl_FragCoord.y));
+    float gap = 1.0;"#,
+        literal(constants.output_size[1] - 1.0)
+    )?;
+    if let Some(tube) = constants.tube {
+        let factor = literal(tube.factor);
+        writeln!(
+            out,
+            r#"    vec2 cell = pixel - vec2({}, {});
+    pixel = floor((cell + 0.5) / {factor});
+    if (any(lessThan(cell, vec2(0.0))) || any(greaterThanEqual(pixel, RENDER_SIZE))) {{
+        frag_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }}
+    if (cell.y - pixel.y * {factor} == {factor} - 1.0) {{
+        gap = {};
+    }}"#,
+            literal(tube.offset[0]),
+            literal(tube.offset[1]),
+            literal(if tube.factor > 1.0 {
+                tube.gap_scale
+            } else {
+                1.0
+            })
+        )?;
+    }
+    writeln!(out, "\n    float level = clamp(u_level, 0.0, 1.0);")?;
+    program.write(out, ShaderLanguage::GlslEs)?;
+    writeln!(
+        out,
+        "\n    vec4 color = vec4(background_r, background_g, background_b, 255.0);"
+    )?;
+
+    for &phase in &constants.phases {
+        writeln!(out, "    if (phase == {}) {{", literal(phase as f32))?;
+        match phase {
+            0 => writeln!(out, "        color = fetch(pixel);")?,
+            1 => {
+                writeln!(out, "        color.a = 0.0;")?;
+                for channel in CHANNELS {
+                    writeln!(
+                        out,
+                        r#"        vec4 quad_{channel} = vec4(quad_{channel}_x, quad_{channel}_y, quad_{channel}_w, quad_{channel}_h);
+        if (inside(pixel, quad_{channel})) {{
+            color.{channel} = fetch(stretched(pixel, quad_{channel})).{channel};
+            color.a = 255.0;
+        }}"#
+                    )?;
+                }
+                writeln!(
+                    out,
+                    "        color.rgb = min(color.rgb + vec3(highlight_r, highlight_g, highlight_b), 255.0);"
+                )?;
+            }
+            2 => writeln!(
+                out,
+                r#"        if (inside(pixel, vec4(line_x0, {y0}, line_x1 - line_x0, {h}))) {{
+            color.rgb = vec3(line_r, line_g, line_b);
+        }}"#,
+                y0 = literal(constants.line_rows[0]),
+                h = literal(constants.line_rows[1] - constants.line_rows[0]),
+            )?,
+            3 => writeln!(
+                out,
+                r#"        vec2 from_center = vec2(pixel.x + 0.5, (pixel.y + 0.5) / {}) - vec2({}, {});
+        float coverage = clamp(dot_radius - length(from_center) + 0.5, 0.0, 1.0);
+        color.rgb = floor(min(color.rgb + vec3(dot_r, dot_g, dot_b) * coverage, 255.0));"#,
+                literal(constants.pixel_aspect_ratio),
+                literal(constants.dot_center[0]),
+                literal(constants.dot_center[1])
+            )?,
+            4 => writeln!(
+                out,
+                r#"        color = fetch(pixel);
+        color.a = floor(color.a * fade_alpha);"#
+            )?,
+            _ => writeln!(
+                out,
+                r#"        vec4 quad = vec4(scale_x, scale_y, scale_w, scale_h);
+        if (inside(pixel, quad)) {{
+            vec2 uv = (pixel - quad.xy + 0.5) / quad.zw;
+            float lod = log2(max(RENDER_SIZE.x / quad.z, RENDER_SIZE.y / quad.w));
+            vec4 texel = tint(floor(textureLod(u_source, uv, lod) * 255.0 + 0.5));
+            color = vec4(floor(texel.rgb * scale_dim), texel.a);
+        }}"#
+            )?,
+        }
+        writeln!(out, "    }}")?;
+    }
+
+    writeln!(
+        out,
+        "\n    frag_color = vec4(floor(color.rgb * gap), color.a) / 255.0;\n}}"
+    )
+}
+
+fn write_wgsl(out: &mut String, program: &Program, constants: &Constants) -> fmt::Result {
+    let [rw, rh] = constants.render_size;
+    write_header(out, constants)?;
+    writeln!(
+        out,
+        r#"
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct Uniforms {{
+    level: f32,
+}}
+
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+
+const RENDER_SIZE = vec2<f32>({}, {});
+"#,
+        literal(rw),
+        literal(rh)
+    )?;
+
+    writeln!(
+        out,
+        "// Source colour, in 0..255\nfn tint(texel: vec4<f32>) -> vec4<f32> {{"
+    )?;
+    match constants.phosphor {
+        Some([r, g, b]) => writeln!(
+            out,
+            "    let luma = dot(texel.rgb, vec3<f32>(0.299, 0.587, 0.114)) / 255.0;
+    return vec4<f32>(floor(vec3<f32>({}, {}, {}) * luma), texel.a);",
+            literal(r),
+            literal(g),
+            literal(b)
+        )?,
+        None => writeln!(out, "    return texel;")?,
+    }
+    writeln!(
+        out,
+        r#"}}
+
+fn fetch(pixel: vec2<f32>) -> vec4<f32> {{
+    return tint(floor(textureLoad(source, vec2<i32>(pixel), 0) * 255.0 + 0.5));
+}}
+
+fn inside(pixel: vec2<f32>, quad: vec4<f32>) -> bool {{
+    return all(pixel >= quad.xy) && all(pixel < quad.xy + quad.zw);
+}}
+
+// Source pixel shown at a pixel of a quad the source is stretched over
+fn stretched(pixel: vec2<f32>, quad: vec4<f32>) -> vec2<f32> {{
+    return floor(clamp((pixel - quad.xy) / quad.zw * RENDER_SIZE, vec2<f32>(0.0), RENDER_SIZE - 1.0));
+}}
+
+@fragment
+fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {{
+    var pixel = floor(frag_coord.xy);
+    var gap = 1.0;"#
+    )?;
+    if let Some(tube) = constants.tube {
+        let factor = literal(tube.factor);
+        writeln!(
+            out,
+            r#"    let cell = pixel - vec2<f32>({}, {});
+    pixel = floor((cell + 0.5) / {factor});
+    if any(cell < vec2<f32>(0.0)) || any(pixel >= RENDER_SIZE) {{
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }}
+    if cell.y - pixel.y * {factor} == {factor} - 1.0 {{
+        gap = {};
+    }}"#,
+            literal(tube.offset[0]),
+            literal(tube.offset[1]),
+            literal(if tube.factor > 1.0 {
+                tube.gap_scale
+            } else {
+                1.0
+            })
+        )?;
+    }
+    writeln!(out, "\n    let level = clamp(uniforms.level, 0.0, 1.0);")?;
+    program.write(out, ShaderLanguage::Wgsl)?;
+    writeln!(
+        out,
+        "\n    var color = vec4<f32>(background_r, background_g, background_b, 255.0);"
+    )?;
+
+    for &phase in &constants.phases {
+        writeln!(out, "    if phase == {} {{", literal(phase as f32))?;
+        match phase {
+            0 => writeln!(out, "        color = fetch(pixel);")?,
+            1 => {
+                writeln!(out, "        color.a = 0.0;")?;
+                for channel in CHANNELS {
+                    writeln!(
+                        out,
+                        r#"        let quad_{channel} = vec4<f32>(quad_{channel}_x, quad_{channel}_y, quad_{channel}_w, quad_{channel}_h);
+        if inside(pixel, quad_{channel}) {{
+            color.{channel} = fetch(stretched(pixel, quad_{channel})).{channel};
+            color.a = 255.0;
+        }}"#
+                    )?;
+                }
+                writeln!(
+                    out,
+                    "        color = vec4<f32>(min(color.rgb + vec3<f32>(highlight_r, highlight_g, highlight_b), vec3<f32>(255.0)), color.a);"
+                )?;
+            }
+            2 => writeln!(
+                out,
+                r#"        if inside(pixel, vec4<f32>(line_x0, {y0}, line_x1 - line_x0, {h})) {{
+            color = vec4<f32>(line_r, line_g, line_b, color.a);
+        }}"#,
+                y0 = literal(constants.line_rows[0]),
+                h = literal(constants.line_rows[1] - constants.line_rows[0]),
+            )?,
+            3 => writeln!(
+                out,
+                r#"        let from_center = vec2<f32>(pixel.x + 0.5, (pixel.y + 0.5) / {}) - vec2<f32>({}, {});
+        let coverage = clamp(dot_radius - length(from_center) + 0.5, 0.0, 1.0);
+        color = vec4<f32>(floor(min(color.rgb + vec3<f32>(dot_r, dot_g, dot_b) * coverage, vec3<f32>(255.0))), color.a);"#,
+                literal(constants.pixel_aspect_ratio),
+                literal(constants.dot_center[0]),
+                literal(constants.dot_center[1])
+            )?,
+            4 => writeln!(
+                out,
+                r#"        color = fetch(pixel);
+        color.a = floor(color.a * fade_alpha);"#
+            )?,
+            _ => writeln!(
+                out,
+                r#"        let quad = vec4<f32>(scale_x, scale_y, scale_w, scale_h);
+        if inside(pixel, quad) {{
+            let uv = (pixel - quad.xy + 0.5) / quad.zw;
+            let lod = log2(max(RENDER_SIZE.x / quad.z, RENDER_SIZE.y / quad.w));
+            let texel = tint(floor(textureSampleLevel(source, source_sampler, uv, lod) * 255.0 + 0.5));
+            color = vec4<f32>(floor(texel.rgb * scale_dim), texel.a);
+        }}"#
+            )?,
+        }
+        writeln!(out, "    }}")?;
+    }
+
+    writeln!(
+        out,
+        "\n    return vec4<f32>(floor(color.rgb * gap), color.a) / 255.0;\n}}"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mip::MipPyramid;
+    use crate::{ElectronBeamBuilder, Keyframe, Phosphor, Timeline, TubeResolution};
+    use image::{Rgba, RgbaImage};
+
+    fn source(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([
+                (x * 5) as u8,
+                (y * 7) as u8,
+                ((x + y) * 3) as u8,
+                255 - x as u8,
+            ])
+        })
+    }
+
+    /// Configurations covering every phase and template option
+    fn beams() -> Vec<PreparedBeam> {
+        let every_phase: Timeline =
+            "hold:1,vstretch:2:ease-in,hstretch:1:ease-in-out,dot:1,fade:1,scale:2:ease-out"
+                .parse()
+                .unwrap();
+        vec![
+            ElectronBeamBuilder::new()
+                .dimensions(48, 36)
+                .prepare(source(48, 36))
+                .unwrap(),
+            ElectronBeamBuilder::new()
+                .dimensions(48, 36)
+                .pixel_aspect_ratio(1.25)
+                .timeline(every_phase.clone())
+                .highlight(
+                    Animated::keyframes([(0.0, [255, 128, 0]), (0.3, [0, 64, 255])]),
+                    Animated::Keyframes(vec![
+                        Keyframe::new(0.0, 0.2).eased(Easing::EaseOut),
+                        Keyframe::new(0.25, 1.0),
+                    ]),
+                )
+                .chroma_split(Animated::keyframes([(0.1, 0.5), (0.3, 3.0)]))
+                .background(Animated::Keyframes(vec![
+                    Keyframe::new(0.2, [0, 0, 40]).eased(Easing::EaseInOut),
+                    Keyframe::new(0.9, [60, 10, 0]),
+                ]))
+                .prepare(source(48, 36))
+                .unwrap(),
+            ElectronBeamBuilder::new()
+                .dimensions(48, 36)
+                .mode(AnimationMode::WarmUp)
+                .timeline(every_phase)
+                .prepare(source(48, 36))
+                .unwrap(),
+            ElectronBeamBuilder::new()
+                .dimensions(100, 80)
+                .phosphor(Phosphor::Amber)
+                .tube(TubeResolution {
+                    scanline_intensity: 0.5,
+                    ..TubeResolution::new(32, 24)
+                })
+                .timeline("vstretch:1,hstretch:1,dot:1,fade:1".parse().unwrap())
+                .prepare(source(32, 24))
+                .unwrap(),
+        ]
+    }
+
+    /// The generated template, run on the CPU with the values of the program
+    ///
+    /// This is a hand translation of the template in `write_glsl` and
+    /// `write_wgsl`. [`test_generated_shaders_match_snapshots`] fails on any
+    /// change to the emitted code so that it is reviewed against this.
+    fn reference_frame(beam: &PreparedBeam, level: f32) -> RgbaImage {
+        let beam = beam.as_beam();
+        let source = beam.source_image.as_ref().unwrap();
+        let pyramid = MipPyramid::build(source);
+        let program = beam.frame_program().unwrap();
+        let constants = beam.shader_constants().unwrap();
+        let values = program.eval(level);
+        let value = |name: &str| {
+            let index = program.bindings.iter().position(|(n, _)| n == name);
+            values[index.unwrap_or_else(|| panic!("{name} is not bound"))]
+        };
+
+        let [rw, rh] = constants.render_size;
+        let tint = |texel: [f32; 4]| match constants.phosphor {
+            Some(phosphor) => {
+                let luma = (0.299 * texel[0] + 0.587 * texel[1] + 0.114 * texel[2]) / 255.0;
+                let [r, g, b] = phosphor.map(|c| (c * luma).floor());
+                [r, g, b, texel[3]]
+            }
+            None => texel,
+        };
+        let fetch = |[x, y]: [f32; 2]| tint(source.get_pixel(x as u32, y as u32).0.map(f32::from));
+        let inside = |[x, y]: [f32; 2], [qx, qy, qw, qh]: [f32; 4]| {
+            x >= qx && y >= qy && x < qx + qw && y < qy + qh
+        };
+        let stretched = |[x, y]: [f32; 2], [qx, qy, qw, qh]: [f32; 4]| {
+            [
+                ((x - qx) / qw * rw).clamp(0.0, rw - 1.0).floor(),
+                ((y - qy) / qh * rh).clamp(0.0, rh - 1.0).floor(),
+            ]
+        };
+        let quad =
+            |prefix: &str| ["x", "y", "w", "h"].map(|field| value(&format!("{prefix}_{field}")));
+
+        let [ow, oh] = constants.output_size;
+        RgbaImage::from_fn(ow as u32, oh as u32, |x, y| {
+            let mut pixel = [x as f32, y as f32];
+            let mut gap = 1.0;
+            if let Some(tube) = constants.tube {
+                let cell = [pixel[0] - tube.offset[0], pixel[1] - tube.offset[1]];
+                pixel = cell.map(|c| ((c + 0.5) / tube.factor).floor());
+                if cell[0] < 0.0 || cell[1] < 0.0 || pixel[0] >= rw || pixel[1] >= rh {
+                    return Rgba([0, 0, 0, 255]);
+                }
+                if cell[1] - pixel[1] * tube.factor == tube.factor - 1.0 {
+                    gap = tube.gap_scale;
+                }
+            }
+
+            let mut color = [
+                value("background_r"),
+                value("background_g"),
+                value("background_b"),
+                255.0,
+            ];
+            match value("phase") as u8 {
+                0 => color = fetch(pixel),
+                1 => {
+                    color[3] = 0.0;
+                    for (i, channel) in CHANNELS.iter().enumerate() {
+                        let quad = quad(&format!("quad_{channel}"));
+                        if inside(pixel, quad) {
+                            color[i] = fetch(stretched(pixel, quad))[i];
+                            color[3] = 255.0;
+                        }
+                        color[i] = (color[i] + value(&format!("highlight_{channel}"))).min(255.0);
+                    }
+                }
+                2 => {
+                    let [y0, y1] = constants.line_rows;
+                    let (x0, x1) = (value("line_x0"), value("line_x1"));
+                    if inside(pixel, [x0, y0, x1 - x0, y1 - y0]) {
+                        color[..3].copy_from_slice(&CHANNELS.map(|c| value(&format!("line_{c}"))));
+                    }
+                }
+                3 => {
+                    let dx = pixel[0] + 0.5 - constants.dot_center[0];
+                    let dy =
+                        (pixel[1] + 0.5) / constants.pixel_aspect_ratio - constants.dot_center[1];
+                    let coverage =
+                        (value("dot_radius") - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0);
+                    for (i, channel) in CHANNELS.iter().enumerate() {
+                        let dot = value(&format!("dot_{channel}"));
+                        color[i] = (color[i] + dot * coverage).min(255.0).floor();
+                    }
+                }
+                4 => {
+                    color = fetch(pixel);
+                    color[3] = (color[3] * value("fade_alpha")).floor();
+                }
+                _ => {
+                    let quad = quad("scale");
+                    if inside(pixel, quad) {
+                        let u = (pixel[0] - quad[0] + 0.5) / quad[2];
+                        let v = (pixel[1] - quad[1] + 0.5) / quad[3];
+                        let lod = (rw / quad[2]).max(rh / quad[3]).log2();
+                        let texel = tint(
+                            pyramid
+                                .sample(source, u * rw, v * rh, lod)
+                                .map(|c| c.round()),
+                        );
+                        let dim = value("scale_dim");
+                        color = [0, 1, 2, 3].map(|i| match i {
+                            3 => texel[3],
+                            _ => (texel[i] * dim).floor(),
+                        });
+                    }
+                }
+            }
+
+            Rgba([0, 1, 2, 3].map(|i| match i {
+                3 => color[3] as u8,
+                _ => (color[i] * gap).floor() as u8,
+            }))
+        })
+    }
+
+    #[test]
+    fn test_program_matches_phase_at() {
+        for prepared in beams() {
+            let beam = prepared.as_beam();
+            let program = beam.frame_program().unwrap();
+            for step in 0..=100 {
+                let level = step as f32 / 100.0;
+                let values = program.eval(level);
+                let value = |name: &str| {
+                    let index = program.bindings.iter().position(|(n, _)| n == name);
+                    values[index.unwrap()]
+                };
+                let state = beam.phase_at(level).unwrap();
+                assert_eq!(value("phase") as u8, phase_code(&state.phase).unwrap());
+                assert_eq!(value("progress"), state.progress, "level {level}");
+
+                let params = beam.params_at(level);
+                let background = CHANNELS.map(|c| value(&format!("background_{c}")) as u8);
+                assert_eq!(background, params.background);
+
+                match state.phase {
+                    Phase::VStretch => {
+                        for (i, channel) in CHANNELS.iter().enumerate() {
+                            assert_eq!(value(&format!("curve_{channel}")), state.curves[i]);
+                            let quad = state.quads[i];
+                            let fields = [quad.x, quad.y, quad.w, quad.h];
+                            for (field, expected) in ["x", "y", "w", "h"].iter().zip(fields) {
+                                let actual = value(&format!("quad_{channel}_{field}"));
+                                assert!((actual - expected).abs() < 1e-4, "level {level}");
+                            }
+                        }
+                    }
+                    Phase::HStretch => {
+                        let (x0, x1) = (value("line_x0"), value("line_x1"));
+                        match beam.h_stretch_line(state.progress) {
+                            Some((line, color)) => {
+                                assert_eq!((x0, (x1 - x0).max(0.0)), (line.x, line.w));
+                                assert_eq!(
+                                    color,
+                                    CHANNELS.map(|c| value(&format!("line_{c}")) as u8)
+                                );
+                            }
+                            None => assert!(x1 <= x0),
+                        }
+                    }
+                    Phase::Dot => {
+                        assert_eq!(value("dot_radius"), beam.dot_radius(state.progress));
+                    }
+                    Phase::Scale => {
+                        let quad = state.quads[0];
+                        let fields = ["x", "y", "w", "h"].map(|f| value(&format!("scale_{f}")));
+                        assert_eq!(fields, [quad.x, quad.y, quad.w, quad.h]);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reference_evaluation_matches_draw() {
+        for (i, beam) in beams().iter().enumerate() {
+            for step in 0..=40 {
+                let level = step as f32 / 40.0;
+                let expected = beam.draw(level).unwrap();
+                let reference = reference_frame(beam, level);
+                let differing = expected
+                    .pixels()
+                    .zip(reference.pixels())
+                    .filter(|(a, b)| (0..4).any(|c| a[c].abs_diff(b[c]) > 1))
+                    .count();
+                assert_eq!(differing, 0, "configuration {i}, level {level}");
+            }
+        }
+    }
+
+    /// Bindings naga's Vulkan GLSL front end accepts in place of the WebGL ones
+    const VULKAN_DECLARATIONS: &str = "\
+layout(set = 0, binding = 0) uniform texture2D u_source_texture;
+layout(set = 0, binding = 1) uniform sampler u_source_sampler;
+layout(set = 0, binding = 2) uniform Uniforms { float u_level; };
+layout(location = 0) out vec4 frag_color;
+#define u_source sampler2D(u_source_texture, u_source_sampler)
+";
+
+    #[test]
+    fn test_generated_shaders_validate() {
+        let validate = |module: &naga::Module| {
+            naga::valid::Validator::new(
+                naga::valid::ValidationFlags::all(),
+                naga::valid::Capabilities::default(),
+            )
+            .validate(module)
+            .unwrap();
+        };
+
+        for beam in beams() {
+            let wgsl = beam.fragment_shader(ShaderLanguage::Wgsl).unwrap();
+            let module = naga::front::wgsl::parse_str(&wgsl)
+                .unwrap_or_else(|e| panic!("{}\n{wgsl}", e.emit_to_string(&wgsl)));
+            validate(&module);
+
+            let glsl = beam.fragment_shader(ShaderLanguage::GlslEs).unwrap();
+            assert!(glsl.starts_with("#version 300 es\n"));
+            let (_, body) = glsl.split_once("out vec4 frag_color;\n").unwrap();
+            let vulkan = format!("#version 450\n{VULKAN_DECLARATIONS}{body}");
+            let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+            let module = naga::front::glsl::Frontend::default()
+                .parse(&options, &vulkan)
+                .unwrap_or_else(|e| panic!("{}\n{vulkan}", e.emit_to_string(&vulkan)));
+            validate(&module);
+        }
+    }
+
+    /// Check the shaders of [`beams`] against `src/snapshots`
+    ///
+    /// Run with `UPDATE_SNAPSHOTS=1` to rewrite them after changing the
+    /// template, then bring [`reference_frame`] in line with the diff.
+    #[test]
+    fn test_generated_shaders_match_snapshots() {
+        let directory = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/snapshots");
+        let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+        for (i, beam) in beams().iter().enumerate() {
+            for (language, extension) in [
+                (ShaderLanguage::GlslEs, "glsl"),
+                (ShaderLanguage::Wgsl, "wgsl"),
+            ] {
+                // Releases should not invalidate every snapshot
+                let shader = beam.fragment_shader(language).unwrap().replacen(
+                    env!("CARGO_PKG_VERSION"),
+                    "VERSION",
+                    1,
+                );
+                let path = directory.join(format!("shader-{i}.{extension}"));
+                if update {
+                    std::fs::create_dir_all(&directory).unwrap();
+                    std::fs::write(&path, &shader).unwrap();
+                }
+                let expected = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+                assert!(
+                    shader == expected,
+                    "{} is out of date, run with UPDATE_SNAPSHOTS=1\n{shader}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_custom_phase_is_unsupported() {
+        let beam = ElectronBeamBuilder::new()
+            .timeline(Timeline::new().then(Phase::custom("noop", |_, _, _| {}), 1.0))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            beam.fragment_shader(ShaderLanguage::Wgsl),
+            Err(ElectronBeamError::UnsupportedPhase(name)) if name == "noop"
+        ));
+    }
+}
@@ -0,0 +1,206 @@
+//! SIMD pixel kernels
+//!
+//! Row operations on packed RGBA8 pixels used by the renderers. Each kernel
+//! has a scalar reference implementation and an AVX2 one that is picked at
+//! runtime when the CPU supports it. Both produce bit-identical results: the
+//! float paths multiply in single precision and truncate, exactly like the
+//! scalar `as u8` casts.
+
+/// Fill every pixel of a row with the same value
+pub(crate) fn fill(pixels: &mut [u8], pixel: [u8; 4]) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { x86::fill(pixels, pixel) };
+    }
+    scalar::fill(pixels, pixel)
+}
+
+/// Add a colour to the RGB channels of every pixel, saturating at 255
+///
+/// Alpha is left untouched.
+pub(crate) fn add_rgb_saturating(pixels: &mut [u8], rgb: [u8; 3]) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { x86::add_rgb_saturating(pixels, rgb) };
+    }
+    scalar::add_rgb_saturating(pixels, rgb)
+}
+
+/// Copy pixels, scaling their alpha by `factor` (0.0 to 1.0)
+pub(crate) fn scale_alpha(dest: &mut [u8], src: &[u8], factor: f32) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { x86::scale_alpha(dest, src, factor) };
+    }
+    scalar::scale_alpha(dest, src, factor)
+}
+
+/// Copy pixels, scaling their RGB channels by `factor` (0.0 to 1.0)
+pub(crate) fn scale_rgb(dest: &mut [u8], src: &[u8], factor: f32) {
+    #[cfg(target_arch = "x86_64")]
+    if is_x86_feature_detected!("avx2") {
+        // SAFETY: AVX2 support was just checked
+        return unsafe { x86::scale_rgb(dest, src, factor) };
+    }
+    scalar::scale_rgb(dest, src, factor)
+}
+
+/// Portable reference kernels, also used for the tails of the SIMD ones
+mod scalar {
+    pub(super) fn fill(pixels: &mut [u8], pixel: [u8; 4]) {
+        for dest in pixels.chunks_exact_mut(4) {
+            dest.copy_from_slice(&pixel);
+        }
+    }
+
+    pub(super) fn add_rgb_saturating(pixels: &mut [u8], rgb: [u8; 3]) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            for i in 0..3 {
+                pixel[i] = pixel[i].saturating_add(rgb[i]);
+            }
+        }
+    }
+
+    pub(super) fn scale_alpha(dest: &mut [u8], src: &[u8], factor: f32) {
+        for (dest, src) in dest.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+            dest[..3].copy_from_slice(&src[..3]);
+            dest[3] = (src[3] as f32 * factor) as u8;
+        }
+    }
+
+    pub(super) fn scale_rgb(dest: &mut [u8], src: &[u8], factor: f32) {
+        for (dest, src) in dest.chunks_exact_mut(4).zip(src.chunks_exact(4)) {
+            for i in 0..3 {
+                dest[i] = (src[i] as f32 * factor) as u8;
+            }
+            dest[3] = src[3];
+        }
+    }
+}
+
+/// AVX2 kernels, working on eight pixels at a time
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    /// Bytes in one AVX2 register: eight RGBA pixels
+    const LANES: usize = 32;
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn fill(pixels: &mut [u8], pixel: [u8; 4]) {
+        let value = _mm256_set1_epi32(i32::from_le_bytes(pixel));
+        let mut chunks = pixels.chunks_exact_mut(LANES);
+        for chunk in &mut chunks {
+            _mm256_storeu_si256(chunk.as_mut_ptr() as *mut __m256i, value);
+        }
+        super::scalar::fill(chunks.into_remainder(), pixel);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn add_rgb_saturating(pixels: &mut [u8], rgb: [u8; 3]) {
+        let value = _mm256_set1_epi32(i32::from_le_bytes([rgb[0], rgb[1], rgb[2], 0]));
+        let mut chunks = pixels.chunks_exact_mut(LANES);
+        for chunk in &mut chunks {
+            let ptr = chunk.as_mut_ptr() as *mut __m256i;
+            _mm256_storeu_si256(ptr, _mm256_adds_epu8(_mm256_loadu_si256(ptr), value));
+        }
+        super::scalar::add_rgb_saturating(chunks.into_remainder(), rgb);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn scale_alpha(dest: &mut [u8], src: &[u8], factor: f32) {
+        let factor_v = _mm256_set1_ps(factor);
+        let rgb_mask = _mm256_set1_epi32(0x00ff_ffff);
+        let len = dest.len().min(src.len()) / LANES * LANES;
+
+        for offset in (0..len).step_by(LANES) {
+            let pixels = _mm256_loadu_si256(src.as_ptr().add(offset) as *const __m256i);
+            let alpha = _mm256_cvtepi32_ps(_mm256_srli_epi32(pixels, 24));
+            let alpha = _mm256_cvttps_epi32(_mm256_mul_ps(alpha, factor_v));
+            let result = _mm256_or_si256(
+                _mm256_and_si256(pixels, rgb_mask),
+                _mm256_slli_epi32(alpha, 24),
+            );
+            _mm256_storeu_si256(dest.as_mut_ptr().add(offset) as *mut __m256i, result);
+        }
+        super::scalar::scale_alpha(&mut dest[len..], &src[len..], factor);
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn scale_rgb(dest: &mut [u8], src: &[u8], factor: f32) {
+        let factor_v = _mm256_set1_ps(factor);
+        let byte_mask = _mm256_set1_epi32(0xff);
+        let len = dest.len().min(src.len()) / LANES * LANES;
+
+        for offset in (0..len).step_by(LANES) {
+            let pixels = _mm256_loadu_si256(src.as_ptr().add(offset) as *const __m256i);
+            let alpha = _mm256_andnot_si256(_mm256_set1_epi32(0x00ff_ffff), pixels);
+            let r = scale_byte(_mm256_and_si256(pixels, byte_mask), factor_v);
+            let g = scale_byte(
+                _mm256_and_si256(_mm256_srli_epi32(pixels, 8), byte_mask),
+                factor_v,
+            );
+            let b = scale_byte(
+                _mm256_and_si256(_mm256_srli_epi32(pixels, 16), byte_mask),
+                factor_v,
+            );
+            let result = _mm256_or_si256(
+                _mm256_or_si256(r, _mm256_slli_epi32(g, 8)),
+                _mm256_or_si256(_mm256_slli_epi32(b, 16), alpha),
+            );
+            _mm256_storeu_si256(dest.as_mut_ptr().add(offset) as *mut __m256i, result);
+        }
+        super::scalar::scale_rgb(&mut dest[len..], &src[len..], factor);
+    }
+
+    /// Multiply eight byte values held in 32-bit lanes, truncating like `as u8`
+    #[target_feature(enable = "avx2")]
+    unsafe fn scale_byte(values: __m256i, factor: __m256) -> __m256i {
+        _mm256_cvttps_epi32(_mm256_mul_ps(_mm256_cvtepi32_ps(values), factor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+
+    fn random_pixels(rng: &mut rand::rngs::StdRng, pixels: usize) -> Vec<u8> {
+        (0..pixels * 4).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_simd_matches_scalar() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(0x0e1e_c7b0);
+
+        // Lengths around the vector width exercise the scalar tails too
+        for pixels in [0, 1, 7, 8, 9, 31, 64, 203] {
+            let src = random_pixels(&mut rng, pixels);
+            let factor = rng.gen_range(0.0..=1.0);
+            let rgb = [rng.gen(), rng.gen(), rng.gen()];
+
+            let (mut fast, mut slow) = (src.clone(), src.clone());
+            fill(&mut fast, [1, 2, 3, 4]);
+            scalar::fill(&mut slow, [1, 2, 3, 4]);
+            assert_eq!(fast, slow);
+
+            let (mut fast, mut slow) = (src.clone(), src.clone());
+            add_rgb_saturating(&mut fast, rgb);
+            scalar::add_rgb_saturating(&mut slow, rgb);
+            assert_eq!(fast, slow);
+
+            let (mut fast, mut slow) = (vec![0; src.len()], vec![0; src.len()]);
+            scale_alpha(&mut fast, &src, factor);
+            scalar::scale_alpha(&mut slow, &src, factor);
+            assert_eq!(fast, slow);
+
+            let (mut fast, mut slow) = (vec![0; src.len()], vec![0; src.len()]);
+            scale_rgb(&mut fast, &src, factor);
+            scalar::scale_rgb(&mut slow, &src, factor);
+            assert_eq!(fast, slow);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+// Electron beam fragment shader, generated by electron-beam VERSION
+//
+// Draws the frame at the level uniform (0.0 to 1.0) into a 48x36 target.
+// The source texture is the image resized to 48x36.
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct Uniforms {
+    level: f32,
+}
+
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+
+const RENDER_SIZE = vec2<f32>(48.0, 36.0);
+
+// Source colour, in 0..255
+fn tint(texel: vec4<f32>) -> vec4<f32> {
+    return texel;
+}
+
+fn fetch(pixel: vec2<f32>) -> vec4<f32> {
+    return tint(floor(textureLoad(source, vec2<i32>(pixel), 0) * 255.0 + 0.5));
+}
+
+fn inside(pixel: vec2<f32>, quad: vec4<f32>) -> bool {
+    return all(pixel >= quad.xy) && all(pixel < quad.xy + quad.zw);
+}
+
+// Source pixel shown at a pixel of a quad the source is stretched over
+fn stretched(pixel: vec2<f32>, quad: vec4<f32>) -> vec2<f32> {
+    return floor(clamp((pixel - quad.xy) / quad.zw * RENDER_SIZE, vec2<f32>(0.0), RENDER_SIZE - 1.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
+    var pixel = floor(frag_coord.xy);
+    var gap = 1.0;
+
+    let level = clamp(uniforms.level, 0.0, 1.0);
+    let position = level;
+    let segment0_t = min(max((position / 0.5), 0.0), 1.0);
+    let segment1_t = min(max(((position - 0.5) / 0.5), 0.0), 1.0);
+    let phase = select(2.0, 1.0, position < 0.5);
+    let progress = select(segment1_t, segment0_t, position < 0.5);
+    let background_r = 0.0;
+    let background_g = 0.0;
+    let background_b = 0.0;
+    let chroma_split = 1.0;
+    let curve_r = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 7.5)))) - 0.5) / 0.47702265) * 0.5) + 0.5);
+    let quad_r_x = ((48.0 - (48.0 + (48.0 * curve_r))) * 0.5);
+    let quad_r_y = ((36.0 - (36.0 - (36.0 * curve_r))) * 0.5);
+    let quad_r_w = (48.0 + (48.0 * curve_r));
+    let quad_r_h = (36.0 - (36.0 * curve_r));
+    let curve_g = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let quad_g_x = ((48.0 - (48.0 + (48.0 * curve_g))) * 0.5);
+    let quad_g_y = ((36.0 - (36.0 - (36.0 * curve_g))) * 0.5);
+    let quad_g_w = (48.0 + (48.0 * curve_g));
+    let quad_g_h = (36.0 - (36.0 * curve_g));
+    let curve_b = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.5)))) - 0.5) / 0.4859364) * 0.5) + 0.5);
+    let quad_b_x = ((48.0 - (48.0 + (48.0 * curve_b))) * 0.5);
+    let quad_b_y = ((36.0 - (36.0 - (36.0 * curve_b))) * 0.5);
+    let quad_b_w = (48.0 + (48.0 * curve_b));
+    let quad_b_h = (36.0 - (36.0 * curve_b));
+    let highlight_strength = 1.0;
+    let highlight_tint_r = 255.0;
+    let highlight_r = floor(max(min((255.0 * curve_g), 255.0), 0.0));
+    let highlight_tint_g = 255.0;
+    let highlight_g = floor(max(min((255.0 * curve_g), 255.0), 0.0));
+    let highlight_tint_b = 255.0;
+    let highlight_b = floor(max(min((255.0 * curve_g), 255.0), 0.0));
+    let line_curve = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let line_x0 = floor(max(((48.0 - (96.0 * (1.0 - line_curve))) * 0.5), 0.0));
+    let line_x1 = select(0.0, min(floor(max((line_x0 + (96.0 * (1.0 - line_curve))), 0.0)), 48.0), progress < 1.0);
+    let line_r = floor((255.0 * (1.0 - (line_curve * 0.75))));
+    let line_g = floor((255.0 * (1.0 - (line_curve * 0.75))));
+    let line_b = floor((255.0 * (1.0 - (line_curve * 0.75))));
+
+    var color = vec4<f32>(background_r, background_g, background_b, 255.0);
+    if phase == 1.0 {
+        color.a = 0.0;
+        let quad_r = vec4<f32>(quad_r_x, quad_r_y, quad_r_w, quad_r_h);
+        if inside(pixel, quad_r) {
+            color.r = fetch(stretched(pixel, quad_r)).r;
+            color.a = 255.0;
+        }
+        let quad_g = vec4<f32>(quad_g_x, quad_g_y, quad_g_w, quad_g_h);
+        if inside(pixel, quad_g) {
+            color.g = fetch(stretched(pixel, quad_g)).g;
+            color.a = 255.0;
+        }
+        let quad_b = vec4<f32>(quad_b_x, quad_b_y, quad_b_w, quad_b_h);
+        if inside(pixel, quad_b) {
+            color.b = fetch(stretched(pixel, quad_b)).b;
+            color.a = 255.0;
+        }
+        color = vec4<f32>(min(color.rgb + vec3<f32>(highlight_r, highlight_g, highlight_b), vec3<f32>(255.0)), color.a);
+    }
+    if phase == 2.0 {
+        if inside(pixel, vec4<f32>(line_x0, 17.0, line_x1 - line_x0, 3.0)) {
+            color = vec4<f32>(line_r, line_g, line_b, color.a);
+        }
+    }
+
+    return vec4<f32>(floor(color.rgb * gap), color.a) / 255.0;
+}
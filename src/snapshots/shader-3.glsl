@@ -0,0 +1,127 @@
+#version 300 es
+// Electron beam fragment shader, generated by electron-beam VERSION
+//
+// Draws the frame at the level uniform (0.0 to 1.0) into a 100x80 target.
+// The source texture is the image resized to 32x24.
+precision highp float;
+precision highp sampler2D;
+
+uniform sampler2D u_source;
+uniform float u_level;
+out vec4 frag_color;
+
+const vec2 RENDER_SIZE = vec2(32.0, 24.0);
+
+// Source colour, in 0..255
+vec4 tint(vec4 texel) {
+    float luma = dot(texel.rgb, vec3(0.299, 0.587, 0.114)) / 255.0;
+    return vec4(floor(vec3(255.0, 176.0, 0.0) * luma), texel.a);
+}
+
+vec4 fetch(vec2 pixel) {
+    return tint(floor(texelFetch(u_source, ivec2(pixel), 0) * 255.0 + 0.5));
+}
+
+bool inside(vec2 pixel, vec4 quad) {
+    return all(greaterThanEqual(pixel, quad.xy)) && all(lessThan(pixel, quad.xy + quad.zw));
+}
+
+// Source pixel shown at a pixel of a quad the source is stretched over
+vec2 stretched(vec2 pixel, vec4 quad) {
+    return floor(clamp((pixel - quad.xy) / quad.zw * RENDER_SIZE, vec2(0.0), RENDER_SIZE - 1.0));
+}
+
+void main() {
+    // Rows counted from the top, like the frames drawn on the CPU. This
+    // assumes gl_FragCoord has its origin at the bottom left, as in OpenGL.
+    vec2 pixel = vec2(floor(gl_FragCoord.x), 79.0 - floor(gl_FragCoord.y));
+    float gap = 1.0;
+    vec2 cell = pixel - vec2(2.0, 4.0);
+    pixel = floor((cell + 0.5) / 3.0);
+    if (any(lessThan(cell, vec2(0.0))) || any(greaterThanEqual(pixel, RENDER_SIZE))) {
+        frag_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    if (cell.y - pixel.y * 3.0 == 3.0 - 1.0) {
+        gap = 0.5;
+    }
+
+    float level = clamp(u_level, 0.0, 1.0);
+    float position = (level * 4.0);
+    float segment0_t = min(max(position, 0.0), 1.0);
+    float segment1_t = min(max((position - 1.0), 0.0), 1.0);
+    float segment2_t = min(max((position - 2.0), 0.0), 1.0);
+    float segment3_t = min(max((position - 3.0), 0.0), 1.0);
+    float phase = (position < 1.0 ? 1.0 : (position < 2.0 ? 2.0 : (position < 3.0 ? 3.0 : 4.0)));
+    float progress = (position < 1.0 ? segment0_t : (position < 2.0 ? segment1_t : (position < 3.0 ? segment2_t : segment3_t)));
+    float background_r = 0.0;
+    float background_g = 0.0;
+    float background_b = 0.0;
+    float curve_r = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    float quad_r_x = ((32.0 - (32.0 + (32.0 * curve_r))) * 0.5);
+    float quad_r_y = ((24.0 - (24.0 - (24.0 * curve_r))) * 0.5);
+    float quad_r_w = (32.0 + (32.0 * curve_r));
+    float quad_r_h = (24.0 - (24.0 * curve_r));
+    float curve_g = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    float quad_g_x = ((32.0 - (32.0 + (32.0 * curve_g))) * 0.5);
+    float quad_g_y = ((24.0 - (24.0 - (24.0 * curve_g))) * 0.5);
+    float quad_g_w = (32.0 + (32.0 * curve_g));
+    float quad_g_h = (24.0 - (24.0 * curve_g));
+    float curve_b = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    float quad_b_x = ((32.0 - (32.0 + (32.0 * curve_b))) * 0.5);
+    float quad_b_y = ((24.0 - (24.0 - (24.0 * curve_b))) * 0.5);
+    float quad_b_w = (32.0 + (32.0 * curve_b));
+    float quad_b_h = (24.0 - (24.0 * curve_b));
+    float highlight_strength = 1.0;
+    float highlight_r = floor(max(min((255.0 * curve_g), 255.0), 0.0));
+    float highlight_g = floor(max(min((176.0 * curve_g), 255.0), 0.0));
+    float highlight_b = floor(max(min((0.0 * curve_g), 255.0), 0.0));
+    float line_curve = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    float line_x0 = floor(max(((32.0 - (64.0 * (1.0 - line_curve))) * 0.5), 0.0));
+    float line_x1 = (progress < 1.0 ? min(floor(max((line_x0 + (64.0 * (1.0 - line_curve))), 0.0)), 32.0) : 0.0);
+    float line_r = floor((255.0 * (1.0 - (line_curve * 0.75))));
+    float line_g = floor((176.0 * (1.0 - (line_curve * 0.75))));
+    float line_b = floor((0.0 * (1.0 - (line_curve * 0.75))));
+    float dot_radius = (3.0 * (1.0 - progress));
+    float dot_r = (255.0 * (1.0 - progress));
+    float dot_g = (176.0 * (1.0 - progress));
+    float dot_b = (0.0 * (1.0 - progress));
+    float fade_alpha = (1.0 - progress);
+
+    vec4 color = vec4(background_r, background_g, background_b, 255.0);
+    if (phase == 1.0) {
+        color.a = 0.0;
+        vec4 quad_r = vec4(quad_r_x, quad_r_y, quad_r_w, quad_r_h);
+        if (inside(pixel, quad_r)) {
+            color.r = fetch(stretched(pixel, quad_r)).r;
+            color.a = 255.0;
+        }
+        vec4 quad_g = vec4(quad_g_x, quad_g_y, quad_g_w, quad_g_h);
+        if (inside(pixel, quad_g)) {
+            color.g = fetch(stretched(pixel, quad_g)).g;
+            color.a = 255.0;
+        }
+        vec4 quad_b = vec4(quad_b_x, quad_b_y, quad_b_w, quad_b_h);
+        if (inside(pixel, quad_b)) {
+            color.b = fetch(stretched(pixel, quad_b)).b;
+            color.a = 255.0;
+        }
+        color.rgb = min(color.rgb + vec3(highlight_r, highlight_g, highlight_b), 255.0);
+    }
+    if (phase == 2.0) {
+        if (inside(pixel, vec4(line_x0, 11.0, line_x1 - line_x0, 3.0))) {
+            color.rgb = vec3(line_r, line_g, line_b);
+        }
+    }
+    if (phase == 3.0) {
+        vec2 from_center = vec2(pixel.x + 0.5, (pixel.y + 0.5) / 1.0) - vec2(16.0, 12.0);
+        float coverage = clamp(dot_radius - length(from_center) + 0.5, 0.0, 1.0);
+        color.rgb = floor(min(color.rgb + vec3(dot_r, dot_g, dot_b) * coverage, 255.0));
+    }
+    if (phase == 4.0) {
+        color = fetch(pixel);
+        color.a = floor(color.a * fade_alpha);
+    }
+
+    frag_color = vec4(floor(color.rgb * gap), color.a) / 255.0;
+}
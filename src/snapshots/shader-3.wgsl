@@ -0,0 +1,127 @@
+// Electron beam fragment shader, generated by electron-beam VERSION
+//
+// Draws the frame at the level uniform (0.0 to 1.0) into a 100x80 target.
+// The source texture is the image resized to 32x24.
+
+@group(0) @binding(0) var source: texture_2d<f32>;
+@group(0) @binding(1) var source_sampler: sampler;
+
+struct Uniforms {
+    level: f32,
+}
+
+@group(0) @binding(2) var<uniform> uniforms: Uniforms;
+
+const RENDER_SIZE = vec2<f32>(32.0, 24.0);
+
+// Source colour, in 0..255
+fn tint(texel: vec4<f32>) -> vec4<f32> {
+    let luma = dot(texel.rgb, vec3<f32>(0.299, 0.587, 0.114)) / 255.0;
+    return vec4<f32>(floor(vec3<f32>(255.0, 176.0, 0.0) * luma), texel.a);
+}
+
+fn fetch(pixel: vec2<f32>) -> vec4<f32> {
+    return tint(floor(textureLoad(source, vec2<i32>(pixel), 0) * 255.0 + 0.5));
+}
+
+fn inside(pixel: vec2<f32>, quad: vec4<f32>) -> bool {
+    return all(pixel >= quad.xy) && all(pixel < quad.xy + quad.zw);
+}
+
+// Source pixel shown at a pixel of a quad the source is stretched over
+fn stretched(pixel: vec2<f32>, quad: vec4<f32>) -> vec2<f32> {
+    return floor(clamp((pixel - quad.xy) / quad.zw * RENDER_SIZE, vec2<f32>(0.0), RENDER_SIZE - 1.0));
+}
+
+@fragment
+fn fs_main(@builtin(position) frag_coord: vec4<f32>) -> @location(0) vec4<f32> {
+    var pixel = floor(frag_coord.xy);
+    var gap = 1.0;
+    let cell = pixel - vec2<f32>(2.0, 4.0);
+    pixel = floor((cell + 0.5) / 3.0);
+    if any(cell < vec2<f32>(0.0)) || any(pixel >= RENDER_SIZE) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+    if cell.y - pixel.y * 3.0 == 3.0 - 1.0 {
+        gap = 0.5;
+    }
+
+    let level = clamp(uniforms.level, 0.0, 1.0);
+    let position = (level * 4.0);
+    let segment0_t = min(max(position, 0.0), 1.0);
+    let segment1_t = min(max((position - 1.0), 0.0), 1.0);
+    let segment2_t = min(max((position - 2.0), 0.0), 1.0);
+    let segment3_t = min(max((position - 3.0), 0.0), 1.0);
+    let phase = select(select(select(4.0, 3.0, position < 3.0), 2.0, position < 2.0), 1.0, position < 1.0);
+    let progress = select(select(select(segment3_t, segment2_t, position < 3.0), segment1_t, position < 2.0), segment0_t, position < 1.0);
+    let background_r = 0.0;
+    let background_g = 0.0;
+    let background_b = 0.0;
+    let curve_r = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let quad_r_x = ((32.0 - (32.0 + (32.0 * curve_r))) * 0.5);
+    let quad_r_y = ((24.0 - (24.0 - (24.0 * curve_r))) * 0.5);
+    let quad_r_w = (32.0 + (32.0 * curve_r));
+    let quad_r_h = (24.0 - (24.0 * curve_r));
+    let curve_g = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let quad_g_x = ((32.0 - (32.0 + (32.0 * curve_g))) * 0.5);
+    let quad_g_y = ((24.0 - (24.0 - (24.0 * curve_g))) * 0.5);
+    let quad_g_w = (32.0 + (32.0 * curve_g));
+    let quad_g_h = (24.0 - (24.0 * curve_g));
+    let curve_b = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let quad_b_x = ((32.0 - (32.0 + (32.0 * curve_b))) * 0.5);
+    let quad_b_y = ((24.0 - (24.0 - (24.0 * curve_b))) * 0.5);
+    let quad_b_w = (32.0 + (32.0 * curve_b));
+    let quad_b_h = (24.0 - (24.0 * curve_b));
+    let highlight_strength = 1.0;
+    let highlight_r = floor(max(min((255.0 * curve_g), 255.0), 0.0));
+    let highlight_g = floor(max(min((176.0 * curve_g), 255.0), 0.0));
+    let highlight_b = floor(max(min((0.0 * curve_g), 255.0), 0.0));
+    let line_curve = (((((1.0 / (1.0 + exp(((0.0 - (progress - 0.5)) * 8.0)))) - 0.5) / 0.48201376) * 0.5) + 0.5);
+    let line_x0 = floor(max(((32.0 - (64.0 * (1.0 - line_curve))) * 0.5), 0.0));
+    let line_x1 = select(0.0, min(floor(max((line_x0 + (64.0 * (1.0 - line_curve))), 0.0)), 32.0), progress < 1.0);
+    let line_r = floor((255.0 * (1.0 - (line_curve * 0.75))));
+    let line_g = floor((176.0 * (1.0 - (line_curve * 0.75))));
+    let line_b = floor((0.0 * (1.0 - (line_curve * 0.75))));
+    let dot_radius = (3.0 * (1.0 - progress));
+    let dot_r = (255.0 * (1.0 - progress));
+    let dot_g = (176.0 * (1.0 - progress));
+    let dot_b = (0.0 * (1.0 - progress));
+    let fade_alpha = (1.0 - progress);
+
+    var color = vec4<f32>(background_r, background_g, background_b, 255.0);
+    if phase == 1.0 {
+        color.a = 0.0;
+        let quad_r = vec4<f32>(quad_r_x, quad_r_y, quad_r_w, quad_r_h);
+        if inside(pixel, quad_r) {
+            color.r = fetch(stretched(pixel, quad_r)).r;
+            color.a = 255.0;
+        }
+        let quad_g = vec4<f32>(quad_g_x, quad_g_y, quad_g_w, quad_g_h);
+        if inside(pixel, quad_g) {
+            color.g = fetch(stretched(pixel, quad_g)).g;
+            color.a = 255.0;
+        }
+        let quad_b = vec4<f32>(quad_b_x, quad_b_y, quad_b_w, quad_b_h);
+        if inside(pixel, quad_b) {
+            color.b = fetch(stretched(pixel, quad_b)).b;
+            color.a = 255.0;
+        }
+        color = vec4<f32>(min(color.rgb + vec3<f32>(highlight_r, highlight_g, highlight_b), vec3<f32>(255.0)), color.a);
+    }
+    if phase == 2.0 {
+        if inside(pixel, vec4<f32>(line_x0, 11.0, line_x1 - line_x0, 3.0)) {
+            color = vec4<f32>(line_r, line_g, line_b, color.a);
+        }
+    }
+    if phase == 3.0 {
+        let from_center = vec2<f32>(pixel.x + 0.5, (pixel.y + 0.5) / 1.0) - vec2<f32>(16.0, 12.0);
+        let coverage = clamp(dot_radius - length(from_center) + 0.5, 0.0, 1.0);
+        color = vec4<f32>(floor(min(color.rgb + vec3<f32>(dot_r, dot_g, dot_b) * coverage, vec3<f32>(255.0))), color.a);
+    }
+    if phase == 4.0 {
+        color = fetch(pixel);
+        color.a = floor(color.a * fade_alpha);
+    }
+
+    return vec4<f32>(floor(color.rgb * gap), color.a) / 255.0;
+}
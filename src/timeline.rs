@@ -0,0 +1,381 @@
+//! Keyframe timelines
+//!
+//! A [`Timeline`] splits the animation level into an ordered sequence of
+//! phases, each taking a share of the animation and easing its own progress.
+//! Without one the animation follows its mode: a vertical then a horizontal
+//! stretch for the CRT modes, or a single fade or scale.
+
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use image::RgbaImage;
+
+use crate::{AnimationMode, ElectronBeamConfig, ElectronBeamError, Result};
+
+/// Draws a custom phase into a frame of the source dimensions
+///
+/// Receives the source (after composite and phosphor passes) and the eased
+/// progress through the phase, from 0.0 to 1.0. The frame is cleared to
+/// transparent black and reused between draws, so it must not be resized.
+pub type PhaseFn = dyn Fn(&RgbaImage, f32, &mut RgbaImage) + Send + Sync;
+
+/// What the animation draws during part of its timeline
+#[derive(Clone)]
+pub enum Phase {
+    /// The picture unchanged
+    Hold,
+    /// The picture collapsing vertically into a line, with colour separation
+    VStretch,
+    /// The line collapsing horizontally
+    HStretch,
+    /// A dot left in the centre once the line is gone, fading out
+    Dot,
+    /// The picture fading to black (from black when warming up)
+    Fade,
+    /// The picture scaling down while dimming (up when warming up)
+    Scale,
+    /// A phase drawn by the caller
+    Custom {
+        /// Name shown in the timeline string
+        name: String,
+        render: Arc<PhaseFn>,
+    },
+}
+
+impl Phase {
+    /// A phase drawn by `render`, see [`PhaseFn`]
+    pub fn custom<F>(name: impl Into<String>, render: F) -> Self
+    where
+        F: Fn(&RgbaImage, f32, &mut RgbaImage) + Send + Sync + 'static,
+    {
+        Phase::Custom {
+            name: name.into(),
+            render: Arc::new(render),
+        }
+    }
+
+    /// Name of the phase in timeline strings
+    pub fn name(&self) -> &str {
+        match self {
+            Phase::Hold => "hold",
+            Phase::VStretch => "vstretch",
+            Phase::HStretch => "hstretch",
+            Phase::Dot => "dot",
+            Phase::Fade => "fade",
+            Phase::Scale => "scale",
+            Phase::Custom { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Debug for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Phase::Hold => f.write_str("Hold"),
+            Phase::VStretch => f.write_str("VStretch"),
+            Phase::HStretch => f.write_str("HStretch"),
+            Phase::Dot => f.write_str("Dot"),
+            Phase::Fade => f.write_str("Fade"),
+            Phase::Scale => f.write_str("Scale"),
+            Phase::Custom { name, .. } => f.debug_struct("Custom").field("name", name).finish(),
+        }
+    }
+}
+
+impl PartialEq for Phase {
+    /// Custom phases are equal only if they share the same render function
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Phase::Custom { render: a, .. }, Phase::Custom { render: b, .. }) => Arc::ptr_eq(a, b),
+            _ => std::mem::discriminant(self) == std::mem::discriminant(other),
+        }
+    }
+}
+
+/// Easing of the progress through a phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Easing {
+    /// Constant speed
+    #[default]
+    Linear,
+    /// Starts slow, quadratic
+    EaseIn,
+    /// Ends slow, quadratic
+    EaseOut,
+    /// Starts and ends slow, smoothstep
+    EaseInOut,
+}
+
+impl Easing {
+    /// Ease a progress value in the range 0..1
+    pub fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "linear",
+            Easing::EaseIn => "ease-in",
+            Easing::EaseOut => "ease-out",
+            Easing::EaseInOut => "ease-in-out",
+        }
+    }
+}
+
+impl FromStr for Easing {
+    type Err = ElectronBeamError;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.to_ascii_lowercase().replace('_', "-").as_str() {
+            "linear" => Ok(Easing::Linear),
+            "ease-in" => Ok(Easing::EaseIn),
+            "ease-out" => Ok(Easing::EaseOut),
+            "ease-in-out" => Ok(Easing::EaseInOut),
+            _ => Err(ElectronBeamError::InvalidTimelineSpec(name.to_string())),
+        }
+    }
+}
+
+/// One phase of a timeline with its share of the animation
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub phase: Phase,
+    /// Relative duration; durations are normalised by the timeline total
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+/// Ordered sequence of phases the animation level runs through
+///
+/// Written as a comma separated list of `phase:duration[:easing]`, for
+/// example `hold:0.1,vstretch:0.4,hstretch:0.4,dot:0.1`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Timeline {
+    segments: Vec<Segment>,
+}
+
+impl Timeline {
+    /// An empty timeline, to be filled with [`then`](Self::then)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The timeline an animation follows when none is configured
+    pub fn for_config(config: &ElectronBeamConfig) -> Self {
+        match config.mode {
+            AnimationMode::CoolDown | AnimationMode::WarmUp => Self::new()
+                .then(Phase::VStretch, config.v_stretch_duration)
+                .then(Phase::HStretch, config.h_stretch_duration),
+            AnimationMode::Fade => Self::new().then(Phase::Fade, 1.0),
+            AnimationMode::ScaleDown => Self::new().then(Phase::Scale, 1.0),
+        }
+    }
+
+    /// Append a phase with linear easing
+    pub fn then(self, phase: Phase, duration: f32) -> Self {
+        self.then_eased(phase, duration, Easing::Linear)
+    }
+
+    /// Append a phase with the given easing
+    pub fn then_eased(mut self, phase: Phase, duration: f32, easing: Easing) -> Self {
+        self.segments.push(Segment {
+            phase,
+            duration,
+            easing,
+        });
+        self
+    }
+
+    /// The phases in order
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    /// Sum of the relative durations
+    pub fn total_duration(&self) -> f32 {
+        self.segments.iter().map(|segment| segment.duration).sum()
+    }
+
+    /// Whether any segment draws the given kind of phase
+    pub(crate) fn contains(&self, phase: &Phase) -> bool {
+        self.segments.iter().any(|segment| segment.phase == *phase)
+    }
+
+    /// Check that every duration is usable and the timeline is not empty
+    pub fn validate(&self) -> Result<()> {
+        for segment in &self.segments {
+            if !(segment.duration >= 0.0 && segment.duration.is_finite()) {
+                return Err(ElectronBeamError::InvalidPhaseDuration(segment.duration));
+            }
+        }
+        if self.total_duration() <= 0.0 {
+            return Err(ElectronBeamError::EmptyTimeline);
+        }
+        Ok(())
+    }
+
+    /// Find the segment drawn at `level` and the eased progress through it
+    ///
+    /// Phases of zero duration are never drawn. Level 1.0 ends the last phase.
+    pub(crate) fn locate(&self, level: f32) -> (&Segment, f32) {
+        let position = level * self.total_duration();
+        let mut start = 0.0;
+        let mut located = None;
+        for segment in self.segments.iter().filter(|s| s.duration > 0.0) {
+            located = Some((segment, start));
+            if position < start + segment.duration {
+                break;
+            }
+            start += segment.duration;
+        }
+
+        let (segment, start) = located.expect("a validated timeline has a phase");
+        let progress = ((position - start) / segment.duration).clamp(0.0, 1.0);
+        (segment, segment.easing.apply(progress))
+    }
+}
+
+impl fmt::Display for Timeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, segment) in self.segments.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}:{}", segment.phase.name(), segment.duration)?;
+            if segment.easing != Easing::Linear {
+                write!(f, ":{}", segment.easing.name())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Timeline {
+    type Err = ElectronBeamError;
+
+    /// Parse a timeline string such as `vstretch:0.5,hstretch:0.5:ease-out`
+    ///
+    /// Custom phases cannot be written in a string.
+    fn from_str(spec: &str) -> Result<Self> {
+        let invalid = |part: &str| ElectronBeamError::InvalidTimelineSpec(part.to_string());
+
+        let mut timeline = Timeline::new();
+        for part in spec.split(',').map(str::trim) {
+            let mut fields = part.split(':').map(str::trim);
+            let phase = match fields.next().unwrap_or_default() {
+                "hold" => Phase::Hold,
+                "vstretch" => Phase::VStretch,
+                "hstretch" => Phase::HStretch,
+                "dot" => Phase::Dot,
+                "fade" => Phase::Fade,
+                "scale" => Phase::Scale,
+                _ => return Err(invalid(part)),
+            };
+            let duration = fields
+                .next()
+                .and_then(|duration| duration.parse().ok())
+                .ok_or_else(|| invalid(part))?;
+            let easing = match fields.next() {
+                Some(easing) => easing.parse()?,
+                None => Easing::Linear,
+            };
+            if fields.next().is_some() {
+                return Err(invalid(part));
+            }
+            timeline = timeline.then_eased(phase, duration, easing);
+        }
+
+        timeline.validate()?;
+        Ok(timeline)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Timeline {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self
+            .segments
+            .iter()
+            .any(|s| matches!(s.phase, Phase::Custom { .. }))
+        {
+            return Err(serde::ser::Error::custom(
+                "timelines with custom phases cannot be serialized",
+            ));
+        }
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Timeline {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let spec = String::deserialize(deserializer)?;
+        spec.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_display() {
+        let timeline: Timeline = "hold:0.1, vstretch:0.4,hstretch:0.4:ease-out,dot:0.1"
+            .parse()
+            .unwrap();
+
+        let phases: Vec<_> = timeline.segments().iter().map(|s| s.phase.name()).collect();
+        assert_eq!(phases, ["hold", "vstretch", "hstretch", "dot"]);
+        assert_eq!(timeline.segments()[2].easing, Easing::EaseOut);
+        assert_eq!(
+            timeline.to_string(),
+            "hold:0.1,vstretch:0.4,hstretch:0.4:ease-out,dot:0.1"
+        );
+
+        for spec in [
+            "",
+            "warp:1",
+            "hold",
+            "hold:x",
+            "hold:1:bouncy",
+            "hold:1:linear:2",
+        ] {
+            assert!(spec.parse::<Timeline>().is_err(), "{spec:?} parsed");
+        }
+        assert!(matches!(
+            "hold:0,dot:0".parse::<Timeline>(),
+            Err(ElectronBeamError::EmptyTimeline)
+        ));
+        assert!(matches!(
+            "hold:-1,dot:2".parse::<Timeline>(),
+            Err(ElectronBeamError::InvalidPhaseDuration(_))
+        ));
+    }
+
+    #[test]
+    fn test_locate_normalises_durations() {
+        let timeline = Timeline::new()
+            .then(Phase::Hold, 1.0)
+            .then(Phase::VStretch, 0.0)
+            .then_eased(Phase::Fade, 3.0, Easing::EaseIn);
+
+        let (segment, progress) = timeline.locate(0.125);
+        assert_eq!((&segment.phase, progress), (&Phase::Hold, 0.5));
+
+        // The empty v-stretch is skipped
+        let (segment, progress) = timeline.locate(0.25);
+        assert_eq!((&segment.phase, progress), (&Phase::Fade, 0.0));
+        let (_, progress) = timeline.locate(0.625);
+        assert_eq!(progress, 0.25);
+        let (segment, progress) = timeline.locate(1.0);
+        assert_eq!((&segment.phase, progress), (&Phase::Fade, 1.0));
+    }
+}
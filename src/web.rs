@@ -0,0 +1,959 @@
+//! CSS and SVG animation export
+//!
+//! A GIF of the effect is heavy and blurry on a web page. A [`WebAnimation`]
+//! samples the timeline instead and writes it as CSS keyframes or an animated
+//! SVG that transform the source image itself, so the browser draws every
+//! frame at full resolution.
+
+use crate::{AnimationMode, ElectronBeam, ElectronBeamError, Phase, PreparedBeam, Quad, Result};
+use std::fmt::{self, Write};
+use std::time::Duration;
+
+/// Gap left between the last stop of a phase and the first of the next, as
+/// a fraction of the animation
+const PHASE_EDGE: f32 = 1e-5;
+
+/// What the exported layers show at one point of the animation
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stop {
+    /// Position in the animation, from 0.0 to 1.0
+    time: f32,
+    /// Colour behind the picture, RGB from 0 to 255 and alpha from 0 to 1
+    background: [f32; 4],
+    /// Quads the red, green and blue planes of the picture are stretched over
+    planes: [Quad; 3],
+    opacity: f32,
+    brightness: f32,
+    /// Highlight, beam line or dot, added over the picture
+    overlay: Quad,
+    /// Colour of the overlay, as `background`
+    overlay_color: [f32; 4],
+    /// Whether the overlay is the ellipse inscribed in its quad
+    round: bool,
+}
+
+/// A CSS or SVG animation of the effect, see [`ElectronBeam::web_animation`]
+#[derive(Clone)]
+pub struct WebAnimation<'a> {
+    beam: &'a ElectronBeam,
+    href: String,
+    duration: Duration,
+    looping: bool,
+    reversed: bool,
+    steps: u32,
+}
+
+impl ElectronBeam {
+    /// Export the animation for web pages, showing the image at `href`
+    ///
+    /// The image is stretched over the output size. The browser interpolates
+    /// between stops sampled from the timeline, so the animation follows
+    /// [`draw`](Self::draw) closely rather than exactly. Composite artifacts
+    /// and tube scanlines are not exported, and custom timeline phases fail
+    /// with `UnsupportedPhase`.
+    pub fn web_animation(&self, href: impl Into<String>) -> WebAnimation<'_> {
+        WebAnimation {
+            beam: self,
+            href: href.into(),
+            duration: Duration::from_secs(1),
+            looping: false,
+            reversed: false,
+            steps: 16,
+        }
+    }
+}
+
+impl PreparedBeam {
+    /// Export the animation for web pages, see [`ElectronBeam::web_animation`]
+    pub fn web_animation(&self, href: impl Into<String>) -> WebAnimation<'_> {
+        self.as_beam().web_animation(href)
+    }
+}
+
+impl WebAnimation<'_> {
+    /// Set the length of the animation (1 second by default)
+    pub fn duration(self, duration: Duration) -> Self {
+        Self { duration, ..self }
+    }
+
+    /// Repeat the animation forever instead of holding the last frame
+    pub fn looping(self, looping: bool) -> Self {
+        Self { looping, ..self }
+    }
+
+    /// Play the animation from level 1.0 back to 0.0
+    pub fn reversed(self) -> Self {
+        Self {
+            reversed: !self.reversed,
+            ..self
+        }
+    }
+
+    /// Set the number of intervals each phase is sampled in (16 by default)
+    pub fn steps(self, steps: u32) -> Self {
+        Self {
+            steps: steps.max(1),
+            ..self
+        }
+    }
+
+    /// CSS keyframes animating `<div class="electron-beam"></div>`
+    ///
+    /// The `::before` pseudo-element shows the picture and the `::after` one
+    /// the highlight, beam line and dot. CSS cannot separate colour channels,
+    /// so the picture follows the green plane and phosphors are not tinted.
+    pub fn css(&self) -> Result<String> {
+        let stops = self.stops()?;
+        let mut css = String::new();
+        self.write_css(&mut css, &stops)
+            .expect("writing to a String cannot fail");
+        Ok(css)
+    }
+
+    /// An SVG document animated with SMIL
+    ///
+    /// The colour planes are separate images added together, filtered down
+    /// to one channel or to the phosphor colour.
+    pub fn svg(&self) -> Result<String> {
+        let stops = self.stops()?;
+        let mut svg = String::new();
+        self.write_svg(&mut svg, &stops)
+            .expect("writing to a String cannot fail");
+        Ok(svg)
+    }
+
+    /// Sample every phase of the timeline, in playing order
+    fn stops(&self) -> Result<Vec<Stop>> {
+        let timeline = &self.beam.timeline;
+        let total = timeline.total_duration();
+        let segments: Vec<_> = timeline
+            .segments()
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.duration > 0.0)
+            .collect();
+
+        let mut stops = Vec::new();
+        let mut start = 0.0;
+        for (n, &(index, segment)) in segments.iter().enumerate() {
+            let (from, to) = (start / total, (start + segment.duration) / total);
+            // The next phase starts right after the end of this one. Stops
+            // of a phase shorter than the gap all share its start time
+            let end = match n + 1 < segments.len() {
+                true => (to - PHASE_EDGE).max(from),
+                false => 1.0,
+            };
+            for step in 0..=self.steps {
+                let t = step as f32 / self.steps as f32;
+                let time = match step == self.steps {
+                    true => end,
+                    false => (from + (to - from) * t).min(end),
+                };
+                stops.push(self.stop(time, index, segment.easing.apply(t))?);
+            }
+            start += segment.duration;
+        }
+
+        if self.reversed {
+            stops.reverse();
+            for stop in &mut stops {
+                stop.time = 1.0 - stop.time;
+            }
+        }
+        Ok(stops)
+    }
+
+    /// The layers at `time`, the eased `progress` through a segment
+    fn stop(&self, time: f32, index: usize, progress: f32) -> Result<Stop> {
+        let beam = self.beam;
+        let config = &beam.config;
+        let state = beam.phase_state(time, index, progress);
+        let params = beam.params_at(time);
+        let (width, height) = beam.render_size();
+        let frame = Quad {
+            x: 0.0,
+            y: 0.0,
+            w: width as f32,
+            h: height as f32,
+        };
+        let rgba = |color: [u8; 3], alpha| {
+            let [r, g, b] = color.map(f32::from);
+            [r, g, b, alpha]
+        };
+
+        let mut stop = Stop {
+            time,
+            background: rgba(params.background, 1.0),
+            planes: [frame; 3],
+            opacity: 1.0,
+            brightness: 1.0,
+            overlay: frame,
+            overlay_color: [0.0; 4],
+            round: false,
+        };
+        match &state.phase {
+            Phase::Hold => {}
+            Phase::VStretch => {
+                // The frame is cleared to transparent and the highlight only
+                // shows over the planes
+                stop.background[3] = 0.0;
+                stop.planes = state.quads;
+                stop.overlay = union(&state.quads);
+                let highlight = if config.mode == AnimationMode::CoolDown {
+                    beam.highlight_color(&params, state.curves[1])
+                } else {
+                    [0, 0, 0]
+                };
+                stop.overlay_color = rgba(highlight, 1.0);
+            }
+            Phase::HStretch => {
+                stop.planes = state.quads;
+                stop.opacity = 0.0;
+                stop.overlay = state.quads[1];
+                if let Some((line, color)) = beam.h_stretch_line(progress) {
+                    stop.overlay = line;
+                    stop.overlay_color = rgba(color, 1.0);
+                }
+            }
+            Phase::Dot => {
+                stop.planes = state.quads;
+                stop.opacity = 0.0;
+                stop.overlay = state.quads[1];
+                let [r, g, b] = beam.beam_color().map(|c| c as f32 * (1.0 - progress));
+                stop.overlay_color = [r, g, b, 1.0];
+                stop.round = true;
+            }
+            Phase::Fade => {
+                stop.background[3] = 0.0;
+                stop.opacity = if config.mode == AnimationMode::WarmUp {
+                    progress
+                } else {
+                    1.0 - progress
+                };
+            }
+            Phase::Scale => {
+                let (curved_scale, scale) = beam.scale_down_curve(progress);
+                stop.planes = [state.quads[0]; 3];
+                stop.brightness = if config.mode == AnimationMode::WarmUp {
+                    scale
+                } else {
+                    scale * (1.0 - curved_scale * 0.5)
+                };
+            }
+            Phase::Custom { name, .. } => {
+                return Err(ElectronBeamError::UnsupportedPhase(name.clone()))
+            }
+        }
+
+        // Place tube frames where they are upscaled to
+        if let Some(tube) = config.tube {
+            let (factor, offset_x, offset_y) = beam.tube_placement(tube);
+            let place = |quad: Quad| Quad {
+                x: offset_x as f32 + quad.x * factor as f32,
+                y: offset_y as f32 + quad.y * factor as f32,
+                w: quad.w * factor as f32,
+                h: quad.h * factor as f32,
+            };
+            stop.planes = stop.planes.map(place);
+            stop.overlay = place(stop.overlay);
+        }
+        Ok(stop)
+    }
+
+    /// Size of the animation in CSS pixels
+    fn size(&self) -> (f32, f32) {
+        let config = &self.beam.config;
+        (config.width as f32, config.height as f32)
+    }
+
+    /// Length and repetition, as the CSS `animation` shorthand puts them
+    fn timing(&self) -> String {
+        let repeat = if self.looping { "infinite" } else { "forwards" };
+        format!(
+            "{}s linear {repeat}",
+            number(self.duration.as_secs_f32(), 3)
+        )
+    }
+
+    fn write_css(&self, out: &mut String, stops: &[Stop]) -> fmt::Result {
+        let (width, height) = self.size();
+        let timing = self.timing();
+        let href = self.href.replace('\\', "\\\\").replace('"', "\\\"");
+        writeln!(
+            out,
+            "/* Electron beam animation, generated by electron-beam {} */",
+            env!("CARGO_PKG_VERSION")
+        )?;
+        writeln!(
+            out,
+            r#"/* Markup: <div class="electron-beam"></div> */
+.electron-beam {{
+  position: relative;
+  width: {width}px;
+  height: {height}px;
+  overflow: hidden;
+  isolation: isolate;
+  animation: electron-beam-screen {timing};
+}}
+
+.electron-beam::before,
+.electron-beam::after {{
+  content: "";
+  position: absolute;
+  inset: 0;
+  transform-origin: 0 0;
+  animation: {timing};
+}}
+
+.electron-beam::before {{
+  background: url("{href}") 0 0 / 100% 100% no-repeat;
+  image-rendering: pixelated;
+  animation-name: electron-beam-picture;
+}}
+
+.electron-beam::after {{
+  mix-blend-mode: plus-lighter;
+  animation-name: electron-beam-beam;
+}}"#
+        )?;
+
+        let transform = |quad: Quad| {
+            format!(
+                "translate({}%, {}%) scale({}, {})",
+                number(quad.x / width * 100.0, 3),
+                number(quad.y / height * 100.0, 3),
+                number(quad.w / width, 5),
+                number(quad.h / height, 5)
+            )
+        };
+        let keyframes = |out: &mut String, name: &str, f: &dyn Fn(&Stop) -> String| {
+            writeln!(out, "\n@keyframes {name} {{")?;
+            for stop in stops {
+                let percent = number(stop.time * 100.0, 3);
+                writeln!(out, "  {percent}% {{ {} }}", f(stop))?;
+            }
+            writeln!(out, "}}")
+        };
+        keyframes(out, "electron-beam-screen", &|stop| {
+            format!("background-color: {};", css_color(stop.background))
+        })?;
+        keyframes(out, "electron-beam-picture", &|stop| {
+            format!(
+                "transform: {}; opacity: {}; filter: brightness({});",
+                transform(stop.planes[1]),
+                number(stop.opacity, 3),
+                number(stop.brightness, 3)
+            )
+        })?;
+        keyframes(out, "electron-beam-beam", &|stop| {
+            format!(
+                "transform: {}; background-color: {}; border-radius: {};",
+                transform(stop.overlay),
+                css_color(stop.overlay_color),
+                if stop.round { "50%" } else { "0" }
+            )
+        })
+    }
+
+    fn write_svg(&self, out: &mut String, stops: &[Stop]) -> fmt::Result {
+        let (width, height) = self.size();
+        let (width, height) = (number(width, 3), number(height, 3));
+        let href = xml_escape(&self.href);
+        let key_times = stops
+            .iter()
+            .map(|stop| number(stop.time, 6))
+            .collect::<Vec<_>>()
+            .join(";");
+        let repeat = if self.looping {
+            r#"repeatCount="indefinite""#
+        } else {
+            r#"fill="freeze""#
+        };
+        let timing = format!(
+            r#"dur="{}s" {repeat} calcMode="linear" keyTimes="{key_times}""#,
+            number(self.duration.as_secs_f32(), 3)
+        );
+        let svg = Svg {
+            timing: &timing,
+            stops,
+        };
+
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        )?;
+        writeln!(
+            out,
+            "  <!-- Electron beam animation, generated by electron-beam {} -->",
+            env!("CARGO_PKG_VERSION")
+        )?;
+
+        // One filter per colour plane, keeping its channel or its share of
+        // the phosphor colour
+        writeln!(out, "  <defs>")?;
+        for (channel, name) in ["red", "green", "blue"].iter().enumerate() {
+            let mut matrix = [[0.0; 5]; 4];
+            matrix[3][3] = 1.0;
+            match self.beam.config.phosphor {
+                Some(phosphor) => {
+                    let color = phosphor.color()[channel] as f32 / 255.0;
+                    matrix[channel][..3]
+                        .copy_from_slice(&[0.299, 0.587, 0.114].map(|luma| color * luma));
+                }
+                None => matrix[channel][channel] = 1.0,
+            }
+            let values = matrix
+                .iter()
+                .flatten()
+                .map(|&value| number(value, 5))
+                .collect::<Vec<_>>()
+                .join(" ");
+            writeln!(
+                out,
+                r#"    <filter id="electron-beam-{name}" color-interpolation-filters="sRGB">
+      <feColorMatrix type="matrix" values="{values}"/>
+    </filter>"#
+            )?;
+        }
+        writeln!(out, "  </defs>")?;
+
+        svg.element(
+            out,
+            1,
+            "rect",
+            &[("width", width.clone()), ("height", height.clone())],
+            &[
+                ("fill", &|stop| svg_color(stop.background)),
+                ("fill-opacity", &|stop| number(stop.background[3], 3)),
+            ],
+        )?;
+
+        // The planes add up to the picture in an isolated group
+        svg.open(
+            out,
+            1,
+            "g",
+            &[("style", "isolation: isolate".to_string())],
+            &[("opacity", &|stop| number(stop.opacity, 3))],
+        )?;
+        for (channel, name) in ["red", "green", "blue"].iter().enumerate() {
+            let plane = move |stop: &Stop| stop.planes[channel];
+            svg.element(
+                out,
+                2,
+                "image",
+                &[
+                    ("href", href.clone()),
+                    ("preserveAspectRatio", "none".to_string()),
+                    ("filter", format!("url(#electron-beam-{name})")),
+                    (
+                        "style",
+                        "mix-blend-mode: plus-lighter; image-rendering: pixelated".to_string(),
+                    ),
+                ],
+                &[
+                    ("x", &|stop| number(plane(stop).x, 3)),
+                    ("y", &|stop| number(plane(stop).y, 3)),
+                    ("width", &|stop| number(plane(stop).w, 3)),
+                    ("height", &|stop| number(plane(stop).h, 3)),
+                ],
+            )?;
+        }
+        svg.element(
+            out,
+            2,
+            "rect",
+            &[("fill", "black".to_string())],
+            &[
+                ("x", &|stop| number(stop.planes[1].x, 3)),
+                ("y", &|stop| number(stop.planes[1].y, 3)),
+                ("width", &|stop| number(stop.planes[1].w, 3)),
+                ("height", &|stop| number(stop.planes[1].h, 3)),
+                ("opacity", &|stop| number(1.0 - stop.brightness, 3)),
+            ],
+        )?;
+        writeln!(out, "  </g>")?;
+
+        let radius = |stop: &Stop, size: f32| if stop.round { size * 0.5 } else { 0.0 };
+        svg.element(
+            out,
+            1,
+            "rect",
+            &[("style", "mix-blend-mode: plus-lighter".to_string())],
+            &[
+                ("x", &|stop| number(stop.overlay.x, 3)),
+                ("y", &|stop| number(stop.overlay.y, 3)),
+                ("width", &|stop| number(stop.overlay.w, 3)),
+                ("height", &|stop| number(stop.overlay.h, 3)),
+                ("rx", &|stop| number(radius(stop, stop.overlay.w), 3)),
+                ("ry", &|stop| number(radius(stop, stop.overlay.h), 3)),
+                ("fill", &|stop| svg_color(stop.overlay_color)),
+                ("fill-opacity", &|stop| number(stop.overlay_color[3], 3)),
+            ],
+        )?;
+        writeln!(out, "</svg>")
+    }
+}
+
+/// An attribute taking a value per stop
+type AnimatedAttribute<'a> = (&'a str, &'a dyn Fn(&Stop) -> String);
+
+/// Writes SVG elements whose attributes follow the stops
+struct Svg<'a> {
+    timing: &'a str,
+    stops: &'a [Stop],
+}
+
+impl Svg<'_> {
+    /// Write an element with its animations, leaving it open when `open`
+    fn write(
+        &self,
+        out: &mut String,
+        depth: usize,
+        tag: &str,
+        fixed: &[(&str, String)],
+        animated: &[AnimatedAttribute],
+        open: bool,
+    ) -> fmt::Result {
+        let indent = "  ".repeat(depth);
+        write!(out, "{indent}<{tag}")?;
+        for (name, value) in fixed {
+            write!(out, r#" {name}="{value}""#)?;
+        }
+
+        // Attributes that never change are not animated
+        let mut animations = Vec::new();
+        for (name, value) in animated {
+            let values: Vec<_> = self.stops.iter().map(value).collect();
+            write!(out, r#" {name}="{}""#, values[0])?;
+            if values.iter().any(|value| *value != values[0]) {
+                animations.push((name, values.join(";")));
+            }
+        }
+
+        if animations.is_empty() && !open {
+            return writeln!(out, "/>");
+        }
+        writeln!(out, ">")?;
+        for (name, values) in animations {
+            writeln!(
+                out,
+                r#"{indent}  <animate attributeName="{name}" {} values="{values}"/>"#,
+                self.timing
+            )?;
+        }
+        if !open {
+            writeln!(out, "{indent}</{tag}>")?;
+        }
+        Ok(())
+    }
+
+    fn element(
+        &self,
+        out: &mut String,
+        depth: usize,
+        tag: &str,
+        fixed: &[(&str, String)],
+        animated: &[AnimatedAttribute],
+    ) -> fmt::Result {
+        self.write(out, depth, tag, fixed, animated, false)
+    }
+
+    fn open(
+        &self,
+        out: &mut String,
+        depth: usize,
+        tag: &str,
+        fixed: &[(&str, String)],
+        animated: &[AnimatedAttribute],
+    ) -> fmt::Result {
+        self.write(out, depth, tag, fixed, animated, true)
+    }
+}
+
+/// Smallest quad containing all of the given ones
+fn union(quads: &[Quad; 3]) -> Quad {
+    let x = quads.iter().map(|q| q.x).fold(f32::INFINITY, f32::min);
+    let y = quads.iter().map(|q| q.y).fold(f32::INFINITY, f32::min);
+    let right = quads
+        .iter()
+        .map(|q| q.x + q.w)
+        .fold(f32::NEG_INFINITY, f32::max);
+    let bottom = quads
+        .iter()
+        .map(|q| q.y + q.h)
+        .fold(f32::NEG_INFINITY, f32::max);
+    Quad {
+        x,
+        y,
+        w: right - x,
+        h: bottom - y,
+    }
+}
+
+/// A number with at most `decimals` decimals and no trailing zeros
+fn number(value: f32, decimals: usize) -> String {
+    let text = format!("{value:.decimals$}");
+    let text = match text.contains('.') {
+        true => text.trim_end_matches('0').trim_end_matches('.'),
+        false => &text,
+    };
+    match text {
+        "-0" => "0".to_string(),
+        text => text.to_string(),
+    }
+}
+
+fn css_color([r, g, b, a]: [f32; 4]) -> String {
+    format!(
+        "rgba({}, {}, {}, {})",
+        r.round(),
+        g.round(),
+        b.round(),
+        number(a, 3)
+    )
+}
+
+fn svg_color([r, g, b, _]: [f32; 4]) -> String {
+    format!("rgb({},{},{})", r.round(), g.round(), b.round())
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ElectronBeamBuilder, Timeline};
+    use image::{Rgba, RgbaImage};
+
+    fn source() -> RgbaImage {
+        RgbaImage::from_fn(64, 48, |x, y| {
+            Rgba([(x * 4) as u8, (y * 5) as u8, 120, 255])
+        })
+    }
+
+    /// The stop a browser shows at `time`, interpolating between samples
+    fn interpolate(stops: &[Stop], time: f32) -> Stop {
+        let next = stops
+            .partition_point(|stop| stop.time <= time)
+            .min(stops.len() - 1);
+        let (a, b) = (stops[next.saturating_sub(1)], stops[next]);
+        let t = ((time - a.time) / (b.time - a.time)).clamp(0.0, 1.0);
+        let lerp = |x: f32, y: f32| x + (y - x) * t;
+        let quad = |p: Quad, q: Quad| Quad {
+            x: lerp(p.x, q.x),
+            y: lerp(p.y, q.y),
+            w: lerp(p.w, q.w),
+            h: lerp(p.h, q.h),
+        };
+        Stop {
+            time,
+            background: std::array::from_fn(|i| lerp(a.background[i], b.background[i])),
+            planes: std::array::from_fn(|i| quad(a.planes[i], b.planes[i])),
+            opacity: lerp(a.opacity, b.opacity),
+            brightness: lerp(a.brightness, b.brightness),
+            overlay: quad(a.overlay, b.overlay),
+            overlay_color: std::array::from_fn(|i| lerp(a.overlay_color[i], b.overlay_color[i])),
+            round: a.round,
+        }
+    }
+
+    /// Composite the SVG layers of a stop, as premultiplied RGBA from 0 to 1
+    fn composite(stop: &Stop, source: &RgbaImage, x: u32, y: u32) -> [f32; 4] {
+        let (width, height) = (source.width() as f32, source.height() as f32);
+        // Quads cover the pixels whose top left corner they contain, like
+        // the renderer, and the dot is tested at pixel centres
+        let (px, py) = (x as f32, y as f32);
+        let inside = |q: Quad| px >= q.x && px < q.x + q.w && py >= q.y && py < q.y + q.h;
+
+        let mut picture = [0.0; 4];
+        for (channel, plane) in stop.planes.iter().enumerate() {
+            if inside(*plane) {
+                let sx = ((px - plane.x) / plane.w * width).min(width - 1.0);
+                let sy = ((py - plane.y) / plane.h * height).min(height - 1.0);
+                let texel = source.get_pixel(sx as u32, sy as u32);
+                picture[channel] = texel[channel] as f32 / 255.0;
+                picture[3] = 1.0;
+            }
+        }
+        if inside(stop.planes[1]) {
+            picture[..3].iter_mut().for_each(|c| *c *= stop.brightness);
+        }
+        let picture = picture.map(|c| c * stop.opacity);
+
+        let background = stop.background;
+        let mut out: [f32; 4] = std::array::from_fn(|i| match i {
+            3 => picture[3] + background[3] * (1.0 - picture[3]),
+            _ => picture[i] + background[i] / 255.0 * background[3] * (1.0 - picture[3]),
+        });
+
+        let overlay = stop.overlay;
+        let covered = if stop.round {
+            let dx = (px + 0.5 - overlay.x) / overlay.w - 0.5;
+            let dy = (py + 0.5 - overlay.y) / overlay.h - 0.5;
+            dx * dx + dy * dy <= 0.25
+        } else {
+            inside(overlay)
+        };
+        if covered {
+            let alpha = stop.overlay_color[3];
+            for (out, color) in out.iter_mut().zip(&stop.overlay_color[..3]) {
+                *out = (*out + color / 255.0 * alpha).min(1.0);
+            }
+            out[3] = (out[3] + alpha).min(1.0);
+        }
+        out
+    }
+
+    /// Mean difference between the layers and the frame drawn on the CPU,
+    /// premultiplied, from 0 to 255
+    ///
+    /// Edges of interpolated quads may land a pixel away, so every pixel is
+    /// compared with the closest of its neighbours in the frame.
+    fn mean_error(beam: &PreparedBeam, stops: &[Stop], level: f32) -> f32 {
+        let stop = interpolate(stops, level);
+        let frame = beam.draw(level).unwrap();
+        let drawn = |x: u32, y: u32| {
+            let pixel = frame.get_pixel(x, y);
+            let alpha = pixel[3] as f32 / 255.0;
+            [0, 1, 2, 3].map(|i| match i {
+                3 => alpha,
+                _ => pixel[i] as f32 / 255.0 * alpha,
+            })
+        };
+
+        let (width, height) = frame.dimensions();
+        let mut total = 0.0;
+        for (x, y, _) in frame.enumerate_pixels() {
+            let layered = composite(&stop, beam.source(), x, y);
+            let neighbours = (x.saturating_sub(1)..(x + 2).min(width))
+                .flat_map(|nx| (y.saturating_sub(1)..(y + 2).min(height)).map(move |ny| (nx, ny)));
+            total += neighbours
+                .map(|(nx, ny)| {
+                    let drawn = drawn(nx, ny);
+                    (0..4).map(|i| (drawn[i] - layered[i]).abs()).sum::<f32>()
+                })
+                .fold(f32::INFINITY, f32::min);
+        }
+        total * 255.0 / (width * height * 4) as f32
+    }
+
+    #[test]
+    fn test_layers_follow_draw() {
+        let every_phase: Timeline = "hold:1,vstretch:2,hstretch:2:ease-in,dot:1,fade:1,scale:2"
+            .parse()
+            .unwrap();
+        for beam in [
+            ElectronBeamBuilder::new()
+                .dimensions(64, 48)
+                .prepare(source())
+                .unwrap(),
+            ElectronBeamBuilder::new()
+                .dimensions(64, 48)
+                .timeline(every_phase)
+                .highlight([255, 200, 100], 0.6)
+                .prepare(source())
+                .unwrap(),
+        ] {
+            let animation = beam.web_animation("source.png");
+            let stops = animation.stops().unwrap();
+            assert_eq!((stops[0].time, stops[stops.len() - 1].time), (0.0, 1.0));
+            for step in 0..=50 {
+                let level = step as f32 / 50.0;
+                let error = mean_error(&beam, &stops, level);
+                assert!(error < 2.0, "level {level}: mean error {error}");
+            }
+        }
+    }
+
+    /// Read the stops back from CSS keyframes
+    ///
+    /// Every plane follows the one transform of the picture.
+    fn parse_css(css: &str, width: f32, height: f32) -> Vec<Stop> {
+        let keyframes = |name: &str| -> Vec<(f32, String)> {
+            let block = css
+                .split(&format!("@keyframes {name} {{\n"))
+                .nth(1)
+                .unwrap();
+            block[..block.find("\n}").unwrap()]
+                .lines()
+                .map(|line| {
+                    let (percent, declarations) = line.trim().split_once("% { ").unwrap();
+                    (
+                        percent.parse::<f32>().unwrap() / 100.0,
+                        declarations.to_string(),
+                    )
+                })
+                .collect()
+        };
+        // Arguments of the first call of `function` in the declarations
+        let arguments = |declarations: &str, function: &str| -> Vec<f32> {
+            let start = declarations.find(&format!("{function}(")).unwrap() + function.len() + 1;
+            let end = start + declarations[start..].find(')').unwrap();
+            declarations[start..end]
+                .split(',')
+                .map(|argument| argument.trim().trim_end_matches('%').parse().unwrap())
+                .collect()
+        };
+        let value = |declarations: &str, property: &str| -> String {
+            let start = declarations.find(&format!("{property}: ")).unwrap() + property.len() + 2;
+            declarations[start..].split(';').next().unwrap().to_string()
+        };
+        let quad = |declarations: &str| {
+            let translate = arguments(declarations, "translate");
+            let scale = arguments(declarations, "scale");
+            Quad {
+                x: translate[0] / 100.0 * width,
+                y: translate[1] / 100.0 * height,
+                w: scale[0] * width,
+                h: scale[1] * height,
+            }
+        };
+        let color = |declarations: &str| {
+            let declarations = &declarations[declarations.find("background-color").unwrap()..];
+            let rgba = arguments(declarations, "rgba");
+            [rgba[0], rgba[1], rgba[2], rgba[3]]
+        };
+
+        let screen = keyframes("electron-beam-screen");
+        let picture = keyframes("electron-beam-picture");
+        let beam = keyframes("electron-beam-beam");
+        assert!(screen.len() == picture.len() && picture.len() == beam.len());
+        screen
+            .iter()
+            .zip(&picture)
+            .zip(&beam)
+            .map(|(((time, screen), (_, picture)), (_, beam))| Stop {
+                time: *time,
+                background: color(screen),
+                planes: [quad(picture); 3],
+                opacity: value(picture, "opacity").parse().unwrap(),
+                brightness: arguments(picture, "brightness")[0],
+                overlay: quad(beam),
+                overlay_color: color(beam),
+                round: value(beam, "border-radius") == "50%",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_css_follows_draw() {
+        // CSS cannot split the colour planes, so only compare without the split
+        let every_phase: Timeline = "hold:1,vstretch:2,hstretch:2:ease-in,dot:1,fade:1,scale:2"
+            .parse()
+            .unwrap();
+        for beam in [
+            ElectronBeamBuilder::new()
+                .dimensions(64, 48)
+                .chroma_split(0.0)
+                .prepare(source())
+                .unwrap(),
+            ElectronBeamBuilder::new()
+                .dimensions(64, 48)
+                .timeline(every_phase)
+                .highlight([255, 200, 100], 0.6)
+                .chroma_split(0.0)
+                .background([20, 0, 40])
+                .prepare(source())
+                .unwrap(),
+        ] {
+            let css = beam.web_animation("source.png").css().unwrap();
+            let stops = parse_css(&css, 64.0, 48.0);
+            for step in 0..=50 {
+                let level = step as f32 / 50.0;
+                let error = mean_error(&beam, &stops, level);
+                assert!(error < 2.0, "level {level}: mean error {error}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_css_and_svg_output() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .build()
+            .unwrap();
+        let animation = beam
+            .web_animation("crt \"frame\".png")
+            .duration(Duration::from_millis(1500))
+            .steps(4);
+
+        let css = animation.css().unwrap();
+        assert!(css.contains(r#"url("crt \"frame\".png")"#));
+        assert!(css.contains("animation: electron-beam-screen 1.5s linear forwards;"));
+        // Two phases of five stops each
+        let picture = css
+            .split("@keyframes electron-beam-picture")
+            .nth(1)
+            .unwrap();
+        let picture = &picture[..picture.find("@keyframes").unwrap()];
+        assert_eq!(picture.matches("% {").count(), 10);
+        assert!(picture.contains("  0% { transform: translate(0%, 0%) scale(1, 1);"));
+        // The collapsed line leaves an empty quad
+        assert!(picture.contains("  100% { transform: translate(50%, 47.917%) scale(0, 0);"));
+        assert!(picture.contains("  49.999% {") && picture.contains("  50% {"));
+
+        let svg = animation.looping(true).reversed().svg().unwrap();
+        assert!(svg.starts_with("<svg ") && svg.ends_with("</svg>\n"));
+        assert!(svg.contains(r#"href="crt &quot;frame&quot;.png""#));
+        assert_eq!(svg.matches("<image ").count(), 3);
+        for animate in svg.split("<animate ").skip(1) {
+            let list = |name: &str| {
+                let start = animate.find(&format!(r#"{name}=""#)).unwrap() + name.len() + 2;
+                animate[start..]
+                    .split('"')
+                    .next()
+                    .unwrap()
+                    .split(';')
+                    .count()
+            };
+            assert!(animate.contains(r#"repeatCount="indefinite""#));
+            assert_eq!(list("keyTimes"), list("values"));
+        }
+        // Reversed, the beam line shows first
+        let key_times = svg.split(r#"keyTimes=""#).nth(1).unwrap();
+        assert!(key_times.starts_with("0;0.125;"));
+    }
+
+    #[test]
+    fn test_key_times_never_decrease() {
+        let beam = ElectronBeamBuilder::new()
+            .dimensions(64, 48)
+            .timeline("vstretch:1,hstretch:0.000001,dot:1".parse().unwrap())
+            .build()
+            .unwrap();
+        for animation in [
+            beam.web_animation("a.png"),
+            beam.web_animation("a.png").reversed(),
+        ] {
+            let stops = animation.stops().unwrap();
+            assert!(stops.windows(2).all(|pair| pair[0].time <= pair[1].time));
+
+            let svg = animation.svg().unwrap();
+            let key_times = svg.split(r#"keyTimes=""#).nth(1).unwrap();
+            let key_times: Vec<f32> = key_times[..key_times.find('"').unwrap()]
+                .split(';')
+                .map(|time| time.parse().unwrap())
+                .collect();
+            assert_eq!(key_times.len(), stops.len());
+            assert!(key_times.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_custom_phase_is_unsupported() {
+        let beam = ElectronBeamBuilder::new()
+            .timeline(Timeline::new().then(Phase::custom("noop", |_, _, _| {}), 1.0))
+            .build()
+            .unwrap();
+        assert!(matches!(
+            beam.web_animation("a.png").svg(),
+            Err(ElectronBeamError::UnsupportedPhase(name)) if name == "noop"
+        ));
+    }
+}